@@ -0,0 +1,329 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::error::Error;
+use crate::items::Item;
+use crate::request::RESOURCE_TYPES;
+use crate::{queue, request};
+
+/// Sync token that requests a full sync
+const FULL_SYNC_TOKEN: &str = "*";
+
+/// Local copy of Todoist items and projects, serialized as json next to tod.cfg
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Eq, PartialEq)]
+pub struct Cache {
+    pub sync_token: Option<String>,
+    pub items: Vec<Item>,
+    pub projects: Vec<Project>,
+    #[serde(default)]
+    pub labels: Vec<Label>,
+    #[serde(default)]
+    pub sections: Vec<Section>,
+    /// Resource types of the sync that filled the cache
+    #[serde(default)]
+    pub resource_types: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct Project {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub parent_id: Option<String>,
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub inbox_project: bool,
+    #[serde(default)]
+    pub is_deleted: bool,
+    #[serde(default)]
+    pub is_archived: bool,
+}
+
+/// A personal label, shared labels only appear on items
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct Label {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub is_deleted: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct Section {
+    pub id: String,
+    pub name: String,
+    pub project_id: String,
+    #[serde(default)]
+    pub section_order: i64,
+    #[serde(default)]
+    pub is_deleted: bool,
+    #[serde(default)]
+    pub is_archived: bool,
+}
+
+#[derive(Deserialize)]
+struct SyncResponse {
+    sync_token: String,
+    full_sync: bool,
+    #[serde(default)]
+    items: Vec<Item>,
+    #[serde(default)]
+    projects: Vec<Project>,
+    #[serde(default)]
+    labels: Vec<Label>,
+    #[serde(default)]
+    sections: Vec<Section>,
+}
+
+impl Cache {
+    /// Load the cache for a config, an empty cache if none has been written yet
+    pub fn load(config: &Config) -> Result<Cache, Error> {
+        let path = path(config);
+        if !Path::new(&path).exists() {
+            return Ok(Cache::default());
+        }
+
+        let json = fs::read_to_string(&path)
+            .map_err(|err| Error::Config(format!("Could not read cache {}: {}", path, err)))?;
+        serde_json::from_str(&json)
+            .map_err(|err| Error::Parse(format!("Could not parse cache {}: {}", path, err)))
+    }
+
+    pub fn save(&self, config: &Config) -> Result<(), Error> {
+        let path = path(config);
+        let json = serde_json::to_string(self)
+            .map_err(|err| Error::Parse(format!("Could not convert cache to JSON: {}", err)))?;
+
+        fs::write(&path, json)
+            .map_err(|err| Error::Config(format!("Could not write cache {}: {}", path, err)))
+    }
+
+    /// Uncompleted items that belong to a project
+    pub fn items_for_project(&self, project_id: &str) -> Vec<Item> {
+        self.items
+            .iter()
+            .filter(|item| item.project_id == project_id)
+            .cloned()
+            .collect()
+    }
+
+    /// An item from the cache, fetched from Todoist when it is not there
+    pub fn find_item(&self, config: &Config, id: &str) -> Result<Item, Error> {
+        match self.items.iter().find(|item| item.id == id) {
+            Some(item) => Ok(item.clone()),
+            None => request::get_item(config, id),
+        }
+    }
+
+    /// Sections of a project in the order they appear in Todoist
+    pub fn sections_for_project(&self, project_id: &str) -> Vec<Section> {
+        let mut sections: Vec<Section> = self
+            .sections
+            .iter()
+            .filter(|section| section.project_id == project_id)
+            .cloned()
+            .collect();
+        sections.sort_by_key(|section| section.section_order);
+        sections
+    }
+
+    /// Names of personal labels and labels used on items, sorted and without duplicates
+    pub fn label_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .labels
+            .iter()
+            .map(|label| label.name.clone())
+            .chain(self.items.iter().flat_map(|item| item.labels.clone()))
+            .collect();
+        // Label names are case-insensitive, the personal label's spelling is kept
+        names.sort_by_key(|name| name.to_lowercase());
+        names.dedup_by(|name, kept| name.to_lowercase() == kept.to_lowercase());
+        names
+    }
+
+    /// Apply a sync response, replacing everything on a full sync and upserting otherwise
+    fn merge(self, response: SyncResponse) -> Cache {
+        let (items, projects, labels, sections) = if response.full_sync {
+            (Vec::new(), Vec::new(), Vec::new(), Vec::new())
+        } else {
+            (self.items, self.projects, self.labels, self.sections)
+        };
+
+        let items = upsert(items, response.items, |item| &item.id)
+            .into_iter()
+            .filter(|item| !item.is_deleted && !item.checked)
+            .collect();
+        let projects = upsert(projects, response.projects, |project| &project.id)
+            .into_iter()
+            .filter(|project| !project.is_deleted && !project.is_archived)
+            .collect();
+
+        let labels = upsert(labels, response.labels, |label| &label.id)
+            .into_iter()
+            .filter(|label| !label.is_deleted)
+            .collect();
+        let sections = upsert(sections, response.sections, |section| &section.id)
+            .into_iter()
+            .filter(|section| !section.is_deleted && !section.is_archived)
+            .collect();
+
+        Cache {
+            sync_token: Some(response.sync_token),
+            items,
+            projects,
+            labels,
+            sections,
+            resource_types: RESOURCE_TYPES.map(String::from).to_vec(),
+        }
+    }
+}
+
+/// Path of the cache file, tod.cache in the same directory as tod.cfg
+pub fn path(config: &Config) -> String {
+    Path::new(&config.path)
+        .with_extension("cache")
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Replay queued commands, then fetch the changes since the last sync and save them to the cache.
+/// Falls back to the cached data when there is no network connection.
+pub fn sync(config: &Config) -> Result<Cache, Error> {
+    let cache = Cache::load(config)?;
+    let flushed = queue::flush(config);
+    let sync_token = match &cache.sync_token {
+        Some(token) if cache.resource_types == RESOURCE_TYPES => token.clone(),
+        _ => String::from(FULL_SYNC_TOKEN),
+    };
+
+    match flushed.and_then(|_| request::sync(config, &sync_token)) {
+        Ok(json) => {
+            let response: SyncResponse = serde_json::from_str(&json)
+                .map_err(|err| Error::Parse(format!("Could not parse sync response: {:?}", err)))?;
+            let cache = cache.merge(response);
+            cache.save(config)?;
+            Ok(cache)
+        }
+        Err(Error::Network(_)) if cache.sync_token.is_some() => Ok(cache),
+        Err(err) => Err(err),
+    }
+}
+
+/// Get a vector of all items for a project, syncing the cache first
+pub fn items_for_project(config: &Config, project_id: &str) -> Result<Vec<Item>, Error> {
+    Ok(sync(config)?.items_for_project(project_id))
+}
+
+fn upsert<T, F>(existing: Vec<T>, updates: Vec<T>, id: F) -> Vec<T>
+where
+    F: Fn(&T) -> &String,
+{
+    let mut merged = existing;
+    for update in updates {
+        match merged.iter().position(|current| id(current) == id(&update)) {
+            Some(index) => merged[index] = update,
+            None => merged.push(update),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::tests::mock_config;
+    use serde_json::json;
+
+    fn item(id: &str, project_id: &str, checked: bool) -> serde_json::Value {
+        json!({
+            "id": id,
+            "project_id": project_id,
+            "content": format!("Item {}", id),
+            "priority": 1,
+            "checked": checked,
+            "description": "",
+            "due": null,
+            "is_deleted": false,
+        })
+    }
+
+    #[test]
+    fn incremental_sync_merges_delta() {
+        let mut server = mockito::Server::new();
+        let full = server
+            .mock("POST", "/sync/v9/sync")
+            .match_body(mockito::Matcher::PartialJson(json!({"sync_token": "*"})))
+            .with_body(
+                json!({
+                    "sync_token": "abc",
+                    "full_sync": true,
+                    "items": [item("1", "2", false), item("2", "2", false)],
+                    "projects": [{"id": "2", "name": "work"}],
+                })
+                .to_string(),
+            )
+            .create();
+        let (config, _dir) = mock_config(&server.url());
+
+        assert_eq!(items_for_project(&config, "2").unwrap().len(), 2);
+        full.assert();
+
+        let delta = server
+            .mock("POST", "/sync/v9/sync")
+            .match_body(mockito::Matcher::PartialJson(json!({"sync_token": "abc"})))
+            .with_body(
+                json!({
+                    "sync_token": "def",
+                    "full_sync": false,
+                    "items": [item("1", "2", true), item("3", "2", false)],
+                })
+                .to_string(),
+            )
+            .create();
+
+        let ids: Vec<String> = items_for_project(&config, "2")
+            .unwrap()
+            .into_iter()
+            .map(|item| item.id)
+            .collect();
+        delta.assert();
+        assert_eq!(ids, vec!["2", "3"]);
+        assert_eq!(
+            Cache::load(&config).unwrap().sync_token,
+            Some(String::from("def"))
+        );
+    }
+
+    #[test]
+    fn offline_reads_from_cache() {
+        let (config, _dir) = mock_config("http://127.0.0.1:9");
+        let cache = Cache {
+            sync_token: Some(String::from("abc")),
+            items: vec![serde_json::from_value(item("1", "2", false)).unwrap()],
+            ..Cache::default()
+        };
+        cache.save(&config).unwrap();
+
+        assert_eq!(items_for_project(&config, "2").unwrap(), cache.items);
+    }
+
+    #[test]
+    fn label_names_dedup_ignoring_case() {
+        let mut labelled = item("1", "2", false);
+        labelled["labels"] = json!(["phone", "Errands"]);
+        let cache = Cache {
+            labels: vec![Label {
+                id: String::from("5"),
+                name: String::from("Phone"),
+                is_deleted: false,
+            }],
+            items: vec![serde_json::from_value(labelled).unwrap()],
+            ..Cache::default()
+        };
+
+        assert_eq!(cache.label_names(), vec!["Errands", "Phone"]);
+    }
+}
@@ -0,0 +1,332 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use crate::error::Error;
+use crate::items::{DateInfo, DurationInfo, Item};
+use chrono::NaiveDate;
+
+/// A Sync API command, see https://developer.todoist.com/sync/v9/#write-resources
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct Command {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub uuid: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temp_id: Option<String>,
+    pub args: Value,
+}
+
+/// Response to a list of commands posted to the sync endpoint
+#[derive(Deserialize, Debug, Default)]
+pub struct SyncResponse {
+    #[serde(default)]
+    pub sync_status: HashMap<String, Value>,
+    #[serde(default)]
+    pub temp_id_mapping: HashMap<String, String>,
+    /// Uuids of commands that were queued instead of sent, set by queue::submit_all
+    #[serde(skip)]
+    pub queued: HashSet<String>,
+}
+
+/// Outcome of a single command
+#[derive(Debug, Eq, PartialEq)]
+pub enum Status {
+    Ok,
+    Failed(String),
+    /// Not sent yet, waiting in the offline queue
+    Queued,
+}
+
+impl SyncResponse {
+    pub fn status(&self, uuid: &str) -> Status {
+        if self.queued.contains(uuid) {
+            return Status::Queued;
+        }
+        match self.sync_status.get(uuid) {
+            None => Status::Failed(String::from("No status returned by Todoist")),
+            Some(Value::String(status)) if status == "ok" => Status::Ok,
+            Some(Value::Object(error)) => Status::Failed(
+                error
+                    .get("error")
+                    .and_then(Value::as_str)
+                    .map(String::from)
+                    .unwrap_or_else(|| Value::Object(error.clone()).to_string()),
+            ),
+            Some(status) => Status::Failed(status.to_string()),
+        }
+    }
+
+    /// Combine the responses of two batches
+    pub fn merge(self, other: SyncResponse) -> SyncResponse {
+        let mut sync_status = self.sync_status;
+        sync_status.extend(other.sync_status);
+        let mut temp_id_mapping = self.temp_id_mapping;
+        temp_id_mapping.extend(other.temp_id_mapping);
+        let mut queued = self.queued;
+        queued.extend(other.queued);
+
+        SyncResponse {
+            sync_status,
+            temp_id_mapping,
+            queued,
+        }
+    }
+}
+
+/// Fields to change on an item, None leaves a field as it is
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct Changes {
+    pub content: Option<String>,
+    pub description: Option<String>,
+    /// Natural language, i.e. "every monday at 9am" or "no date"
+    pub due_string: Option<String>,
+    /// Replaces all labels on the item
+    pub labels: Option<Vec<String>>,
+    /// Priority as used by the API, 4 is the highest
+    pub priority: Option<u8>,
+    /// Some(None) removes the duration
+    pub duration: Option<Option<DurationInfo>>,
+    /// Some(None) removes the deadline
+    pub deadline: Option<Option<NaiveDate>>,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        *self == Changes::default()
+    }
+}
+
+impl Command {
+    fn new(kind: &str, args: Value) -> Command {
+        Command {
+            kind: String::from(kind),
+            uuid: new_uuid(),
+            temp_id: None,
+            args,
+        }
+    }
+
+    pub fn item_add(content: &str) -> Command {
+        Command {
+            temp_id: Some(new_uuid()),
+            ..Command::new("item_add", json!({ "content": content }))
+        }
+    }
+
+    pub fn item_move(id: &str, project_id: &str) -> Command {
+        Command::new("item_move", json!({"id": id, "project_id": project_id}))
+    }
+
+    pub fn item_move_to_section(id: &str, section_id: &str) -> Command {
+        Command::new("item_move", json!({"id": id, "section_id": section_id}))
+    }
+
+    pub fn item_update(id: &str, changes: &Changes) -> Command {
+        let mut args = json!({ "id": id });
+        if let Some(content) = &changes.content {
+            args["content"] = json!(content);
+        }
+        if let Some(description) = &changes.description {
+            args["description"] = json!(description);
+        }
+        if let Some(due_string) = &changes.due_string {
+            args["due"] = json!({ "string": due_string });
+        }
+        if let Some(labels) = &changes.labels {
+            args["labels"] = json!(labels);
+        }
+        if let Some(priority) = changes.priority {
+            args["priority"] = json!(priority);
+        }
+        if let Some(duration) = &changes.duration {
+            args["duration"] = json!(duration);
+        }
+        if let Some(deadline) = changes.deadline {
+            args["deadline"] =
+                json!(deadline.map(|date| json!({"date": date.format("%Y-%m-%d").to_string()})));
+        }
+        Command::new("item_update", args)
+    }
+
+    pub fn item_update_priority(id: &str, priority: u8) -> Command {
+        Command::new("item_update", json!({"id": id, "priority": priority}))
+    }
+
+    /// Set the due date with natural language, i.e. "tomorrow at 5pm"
+    pub fn item_update_due(id: &str, due_string: &str) -> Command {
+        Command::new(
+            "item_update",
+            json!({"id": id, "due": {"string": due_string}}),
+        )
+    }
+
+    /// Set the due date to an exact date, keeping recurrence when the due date is recurring
+    pub fn item_update_due_date(id: &str, due: &DateInfo) -> Command {
+        Command::new("item_update", json!({"id": id, "due": due_args(due)}))
+    }
+
+    pub fn item_close(id: &str) -> Command {
+        Command::new("item_close", json!({ "id": id }))
+    }
+
+    pub fn item_delete(id: &str) -> Command {
+        Command::new("item_delete", json!({ "id": id }))
+    }
+
+    /// Recreate a deleted item in its project and section, it gets a new id.
+    /// parent_id is the id of the parent, or its temp id when the parent is restored as well.
+    pub fn item_restore(item: &Item, parent_id: Option<&str>) -> Command {
+        let mut args = json!({
+            "content": item.content,
+            "description": item.description,
+            "priority": item.priority,
+            "labels": item.labels,
+        });
+        if !item.project_id.is_empty() {
+            args["project_id"] = json!(item.project_id);
+        }
+        if let Some(section_id) = &item.section_id {
+            args["section_id"] = json!(section_id);
+        }
+        if let Some(parent_id) = parent_id {
+            args["parent_id"] = json!(parent_id);
+        }
+        if let Some(due) = &item.due {
+            args["due"] = due_args(due);
+        }
+        if let Some(duration) = &item.duration {
+            args["duration"] = json!(duration);
+        }
+        if let Some(deadline) = &item.deadline {
+            args["deadline"] = json!({ "date": deadline.date });
+        }
+        Command {
+            temp_id: Some(new_uuid()),
+            ..Command::new("item_add", args)
+        }
+    }
+
+    pub fn item_uncomplete(id: &str) -> Command {
+        Command::new("item_uncomplete", json!({ "id": id }))
+    }
+
+    /// Replace temp ids in args with the real ids returned by the server
+    pub fn resolve_temp_ids(self, mapping: &HashMap<String, String>) -> Command {
+        Command {
+            args: resolve(self.args, mapping),
+            ..self
+        }
+    }
+
+    pub fn fmt(&self) -> String {
+        format!("{} {}", self.kind, self.args)
+    }
+}
+
+pub fn json_to_sync_response(json: String) -> Result<SyncResponse, Error> {
+    serde_json::from_str(&json)
+        .map_err(|err| Error::Parse(format!("Could not parse sync response: {:?}", err)))
+}
+
+/// An exact due date, with the recurrence string when it is recurring so that it keeps recurring
+fn due_args(due: &DateInfo) -> Value {
+    let mut args = json!({ "date": due.date });
+    if let Some(timezone) = &due.timezone {
+        args["timezone"] = json!(timezone);
+    }
+    if due.is_recurring {
+        args["string"] = json!(due.string);
+        if let Some(lang) = &due.lang {
+            args["lang"] = json!(lang);
+        }
+    }
+    args
+}
+
+fn resolve(value: Value, mapping: &HashMap<String, String>) -> Value {
+    match value {
+        Value::String(id) => match mapping.get(&id) {
+            Some(real_id) => Value::String(real_id.to_owned()),
+            None => Value::String(id),
+        },
+        Value::Array(values) => values
+            .into_iter()
+            .map(|value| resolve(value, mapping))
+            .collect(),
+        Value::Object(map) => map
+            .into_iter()
+            .map(|(key, value)| (key, resolve(value, mapping)))
+            .collect(),
+        value => value,
+    }
+}
+
+/// Create a new UUID, required for Todoist API
+fn new_uuid() -> String {
+    Uuid::new_v4().to_string()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    /// Body of a sync response where every command in the request succeeded
+    pub fn all_ok(request: &mockito::Request) -> Vec<u8> {
+        let body: Value = serde_json::from_slice(request.body().unwrap()).unwrap();
+        let sync_status: HashMap<&str, &str> = body["commands"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|command| (command["uuid"].as_str().unwrap(), "ok"))
+            .collect();
+        json!({ "sync_status": sync_status }).to_string().into()
+    }
+
+    #[test]
+    fn missing_status_is_a_failure_unless_queued() {
+        let response = SyncResponse {
+            queued: HashSet::from([String::from("queued")]),
+            ..SyncResponse::default()
+        };
+
+        assert_eq!(response.status("queued"), Status::Queued);
+        assert_eq!(
+            response.status("missing"),
+            Status::Failed(String::from("No status returned by Todoist"))
+        );
+    }
+
+    #[test]
+    fn restore_keeps_recurrence() {
+        let item: Item = serde_json::from_value(json!({
+            "id": "11", "project_id": "2", "content": "Water plants", "priority": 1,
+            "checked": false, "description": "", "is_deleted": false,
+            "due": {"date": "2024-03-01", "is_recurring": true, "timezone": null,
+                    "string": "every day", "lang": "en"},
+            "duration": {"amount": 15, "unit": "minute"},
+            "deadline": {"date": "2024-03-10", "lang": "en"},
+        }))
+        .unwrap();
+        let args = Command::item_restore(&item, None).args;
+
+        assert_eq!(
+            args["due"],
+            json!({"date": "2024-03-01", "string": "every day", "lang": "en"})
+        );
+        assert_eq!(args["duration"], json!({"amount": 15, "unit": "minute"}));
+        assert_eq!(args["deadline"], json!({"date": "2024-03-10"}));
+    }
+
+    #[test]
+    fn temp_ids_resolve_in_args() {
+        let command = Command::item_move("temp", "2");
+        let mapping = [(String::from("temp"), String::from("42"))].into();
+
+        assert_eq!(
+            command.resolve_temp_ids(&mapping).args,
+            json!({"id": "42", "project_id": "2"})
+        );
+    }
+}
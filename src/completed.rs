@@ -0,0 +1,282 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::config::Config;
+use crate::error::Error;
+use crate::output::Format;
+use crate::{output, projects, request, time};
+
+/// Days of history used by stats when --days is not given
+pub const DEFAULT_STATS_DAYS: i64 = 28;
+
+/// Width of the longest bar in a chart
+const BAR_WIDTH: usize = 30;
+
+/// A completed task as returned by completed/get_all
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct CompletedItem {
+    pub task_id: String,
+    pub project_id: String,
+    pub content: String,
+    pub completed_at: DateTime<Utc>,
+}
+
+impl CompletedItem {
+    /// The day it was completed, in the timezone of config
+    fn date(&self, config: &Config) -> NaiveDate {
+        let tz = time::timezone_from_str(&config.timezone);
+        self.completed_at.with_timezone(&tz).date_naive()
+    }
+}
+
+/// Tasks completed between two dates, a week ago to today by default, grouped by day
+pub fn completed(
+    config: &Config,
+    project_name: Option<&str>,
+    since: Option<NaiveDate>,
+    until: Option<NaiveDate>,
+) -> Result<String, Error> {
+    let today = time::today_date(config);
+    let since = since.unwrap_or(today - Duration::days(6));
+    let project_id = match project_name {
+        Some(name) => Some(projects::project_id(config, name)?),
+        None => None,
+    };
+    let until_time = match until {
+        Some(until) => Some(start_of_day(config, until + Duration::days(1))?),
+        None => None,
+    };
+
+    let items = request::get_completed_items(
+        config,
+        project_id.as_deref(),
+        start_of_day(config, since)?,
+        until_time,
+    )?;
+    if config.output != Format::Plain {
+        return output::completed(config, &items);
+    }
+    if items.is_empty() {
+        return Ok(String::from("No completed tasks found"));
+    }
+
+    let mut days: BTreeMap<NaiveDate, Vec<&CompletedItem>> = BTreeMap::new();
+    for item in items.iter() {
+        days.entry(item.date(config)).or_default().push(item);
+    }
+
+    let mut buffer = String::from("Completed").green().to_string();
+    for (date, items) in days.iter().rev() {
+        buffer.push_str(&format!("\n\n{}", time::format_date(date, config).bold()));
+        for item in items {
+            buffer.push_str(&format!(
+                "\n✓ {}{}",
+                item.content,
+                project_label(config, item)
+            ));
+        }
+    }
+    Ok(buffer)
+}
+
+/// Completions per day and week, streaks and a breakdown per project over the last days
+pub fn stats(config: &Config, days: i64) -> Result<String, Error> {
+    let today = time::today_date(config);
+    let first_day = today - Duration::days(days - 1);
+    let items = request::get_completed_items(config, None, start_of_day(config, first_day)?, None)?;
+    let dates: Vec<NaiveDate> = items.iter().map(|item| item.date(config)).collect();
+
+    let mut buffer = format!("Completed in the last {} days: {}", days, items.len())
+        .green()
+        .to_string();
+
+    let daily: Vec<(String, usize)> = (0..days.min(7))
+        .rev()
+        .map(|offset| today - Duration::days(offset))
+        .map(|date| {
+            let label = date.format("%a %Y-%m-%d").to_string();
+            (label, dates.iter().filter(|d| **d == date).count())
+        })
+        .collect();
+    buffer.push_str(&format!("\n\n{}\n{}", "Daily".bold(), chart(&daily)));
+
+    let mut weeks: BTreeMap<NaiveDate, usize> = BTreeMap::new();
+    let mut week = week_start(first_day);
+    while week <= today {
+        weeks.insert(week, 0);
+        week += Duration::days(7);
+    }
+    for date in dates.iter() {
+        *weeks.entry(week_start(*date)).or_default() += 1;
+    }
+    let weekly: Vec<(String, usize)> = weeks
+        .into_iter()
+        .map(|(week, count)| (format!("Week of {}", week.format("%Y-%m-%d")), count))
+        .collect();
+    buffer.push_str(&format!("\n\n{}\n{}", "Weekly".bold(), chart(&weekly)));
+
+    let mut projects: BTreeMap<String, usize> = BTreeMap::new();
+    for item in items.iter() {
+        *projects
+            .entry(project_name(config, &item.project_id))
+            .or_default() += 1;
+    }
+    let mut projects: Vec<(String, usize)> = projects.into_iter().collect();
+    projects.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    if !projects.is_empty() {
+        buffer.push_str(&format!("\n\n{}\n{}", "Projects".bold(), chart(&projects)));
+    }
+
+    let (current, longest) = streaks(&dates.into_iter().collect(), today);
+    buffer.push_str(&format!(
+        "\n\nCurrent streak: {} days, longest: {} days",
+        current, longest
+    ));
+    Ok(buffer)
+}
+
+/// The current streak of days with a completed task, counting from today or yesterday
+/// when nothing is done yet today, and the longest streak
+fn streaks(dates: &BTreeSet<NaiveDate>, today: NaiveDate) -> (i64, i64) {
+    let mut day = if dates.contains(&today) {
+        today
+    } else {
+        today - Duration::days(1)
+    };
+    let mut current = 0;
+    while dates.contains(&day) {
+        current += 1;
+        day -= Duration::days(1);
+    }
+
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for date in dates {
+        run = match previous {
+            Some(previous) if *date - previous == Duration::days(1) => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        previous = Some(*date);
+    }
+    (current, longest)
+}
+
+/// Labelled horizontal bars scaled to the largest count
+fn chart(rows: &[(String, usize)]) -> String {
+    let label_width = rows
+        .iter()
+        .map(|(label, _)| label.chars().count())
+        .max()
+        .unwrap_or(0);
+    let max = rows
+        .iter()
+        .map(|(_, count)| *count)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    rows.iter()
+        .map(|(label, count)| {
+            let bar = "█".repeat(count * BAR_WIDTH / max);
+            format!(
+                "{:<width$} {} {}",
+                label,
+                bar.cyan(),
+                count,
+                width = label_width
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(date.weekday().num_days_from_monday() as i64)
+}
+
+fn project_name(config: &Config, project_id: &str) -> String {
+    config
+        .project_name(project_id)
+        .cloned()
+        .unwrap_or_else(|| String::from(project_id))
+}
+
+fn project_label(config: &Config, item: &CompletedItem) -> String {
+    format!(" #{}", project_name(config, &item.project_id))
+        .bright_black()
+        .to_string()
+}
+
+/// Midnight at the start of a day in the timezone of config
+fn start_of_day(config: &Config, date: NaiveDate) -> Result<DateTime<Utc>, Error> {
+    let tz = time::timezone_from_str(&config.timezone);
+    date.and_hms_opt(0, 0, 0)
+        .and_then(|midnight| midnight.and_local_timezone(tz).earliest())
+        .map(|midnight| midnight.with_timezone(&Utc))
+        .ok_or_else(|| Error::Input(format!("Invalid date {}", date)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::tests::mock_config;
+    use serde_json::json;
+
+    fn date(string: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(string, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn streaks_count_consecutive_days() {
+        let dates: BTreeSet<NaiveDate> = [
+            "2024-03-01",
+            "2024-03-02",
+            "2024-03-03",
+            "2024-03-08",
+            "2024-03-09",
+        ]
+        .iter()
+        .map(|string| date(string))
+        .collect();
+
+        assert_eq!(streaks(&dates, date("2024-03-10")), (2, 3));
+        assert_eq!(streaks(&dates, date("2024-03-09")), (2, 3));
+        assert_eq!(streaks(&dates, date("2024-03-11")), (0, 3));
+    }
+
+    #[test]
+    fn completed_groups_by_day() {
+        let mut server = mockito::Server::new();
+        let (config, _dir) = mock_config(&server.url());
+        let today = time::today_date(&config);
+        let at = |days: i64| {
+            format!(
+                "{}T10:00:00.000000Z",
+                (today - Duration::days(days)).format("%Y-%m-%d")
+            )
+        };
+        let mock = server
+            .mock("POST", "/sync/v9/completed/get_all")
+            .match_body(mockito::Matcher::PartialJson(
+                json!({"project_id": "2", "offset": 0}),
+            ))
+            .with_body(
+                json!({"items": [
+                    {"task_id": "11", "project_id": "2", "content": "Ship", "completed_at": at(0)},
+                    {"task_id": "10", "project_id": "2", "content": "Plan", "completed_at": at(2)},
+                ]})
+                .to_string(),
+            )
+            .create();
+
+        let output = completed(&config, Some("work"), None, None).unwrap();
+
+        mock.assert();
+        assert!(output.starts_with("Completed\n\nToday\n✓ Ship #work\n\n"));
+        assert!(output.ends_with("✓ Plan #work"));
+    }
+}
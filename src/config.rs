@@ -1,4 +1,6 @@
-use crate::{request, time, VERSION};
+use crate::error::Error;
+use crate::scoring::Scoring;
+use crate::{output, request, time, VERSION};
 use colored::*;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
@@ -10,60 +12,163 @@ use std::{fs, io};
 #[derive(Clone, Serialize, Deserialize, Eq, PartialEq, Debug)]
 pub struct Config {
     pub token: String,
-    pub projects: HashMap<String, u32>,
+    pub projects: HashMap<String, Project>,
     pub path: String,
     pub next_id: Option<String>,
     pub timezone: Option<String>,
     pub last_version_check: Option<String>,
+    /// Overrides https://api.todoist.com, i.e. for a local mock server
+    #[serde(default)]
+    pub todoist_url: Option<String>,
+    /// Overrides https://crates.io/api
+    #[serde(default)]
+    pub cargo_url: Option<String>,
+    /// Minutes that a delete can be undone, defaults to 30
+    #[serde(default)]
+    pub undo_minutes: Option<u32>,
+    /// Length of a pomodoro started with start --pomodoro, defaults to 25
+    #[serde(default)]
+    pub pomodoro_minutes: Option<u32>,
+    /// Show progress towards the daily karma goal after complete, defaults to true
+    #[serde(default)]
+    pub goal_progress: Option<bool>,
+    /// Task ids in the order of the last listing, so tasks can be referred to by number
+    #[serde(default)]
+    pub listing: Vec<String>,
+    /// Task ids ranked by the last next, worked through with complete and skip
+    #[serde(default)]
+    pub focus: Vec<String>,
+    /// Named weights for choosing the next task, i.e. "deep work" or "errands"
+    #[serde(default)]
+    pub scoring_profiles: HashMap<String, Scoring>,
+    /// Profile used when --profile is not given, the default weights when not set
+    #[serde(default)]
+    pub scoring_profile: Option<String>,
+    /// Set from the command line for each run
+    #[serde(skip)]
+    pub output: output::Format,
+    /// Weights of the profile chosen for this run
+    #[serde(skip)]
+    pub scoring: Scoring,
+}
+
+/// A Todoist project, keyed by name in Config
+#[derive(Clone, Serialize, Deserialize, Eq, PartialEq, Debug)]
+#[serde(from = "StoredProject")]
+pub struct Project {
+    pub id: String,
+    pub parent_id: Option<String>,
+    pub color: Option<String>,
+}
+
+/// Projects used to be stored as a numeric id only
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredProject {
+    Legacy(u64),
+    Project {
+        id: String,
+        parent_id: Option<String>,
+        color: Option<String>,
+    },
+}
+
+impl From<StoredProject> for Project {
+    fn from(stored: StoredProject) -> Project {
+        match stored {
+            StoredProject::Legacy(id) => Project::new(&id.to_string()),
+            StoredProject::Project {
+                id,
+                parent_id,
+                color,
+            } => Project {
+                id,
+                parent_id,
+                color,
+            },
+        }
+    }
+}
+
+impl Project {
+    pub fn new(id: &str) -> Project {
+        Project {
+            id: String::from(id),
+            parent_id: None,
+            color: None,
+        }
+    }
 }
 
 impl Config {
-    pub fn new(token: &str) -> Result<Config, String> {
-        let projects: HashMap<String, u32> = HashMap::new();
+    pub fn new(token: &str) -> Result<Config, Error> {
+        let projects: HashMap<String, Project> = HashMap::new();
         Ok(Config {
             path: generate_path()?,
             token: String::from(token),
             next_id: None,
             last_version_check: None,
             timezone: None,
+            todoist_url: None,
+            cargo_url: None,
+            undo_minutes: None,
+            pomodoro_minutes: None,
+            goal_progress: None,
+            listing: Vec::new(),
+            focus: Vec::new(),
+            scoring_profiles: HashMap::new(),
+            scoring_profile: None,
+            output: output::Format::Plain,
+            scoring: Scoring::default(),
             projects,
         })
     }
 
-    pub fn create(self) -> Result<Config, String> {
+    pub fn create(self) -> Result<Config, Error> {
         let json = json!(self).to_string();
-        let mut file = fs::File::create(&self.path).or(Err("Could not create file"))?;
+        let mut file =
+            fs::File::create(&self.path).map_err(config_error("Could not create file"))?;
         file.write_all(json.as_bytes())
-            .or(Err("Could not write to file"))?;
-        println!("Config successfully created in {}", &self.path);
+            .map_err(config_error("Could not write to file"))?;
+        eprintln!("Config successfully created in {}", &self.path);
         Ok(self)
     }
 
-    pub fn save(self) -> std::result::Result<String, String> {
+    pub fn save(self) -> Result<String, Error> {
         let json = json!(self);
-        let string = serde_json::to_string_pretty(&json).or(Err("Could not convert to JSON"))?;
+        let string = serde_json::to_string_pretty(&json)
+            .map_err(|err| Error::Parse(format!("Could not convert to JSON: {}", err)))?;
 
         fs::OpenOptions::new()
             .write(true)
             .read(true)
             .truncate(true)
             .open(&self.path)
-            .or(Err("Could not find config"))?
+            .map_err(config_error("Could not find config"))?
             .write_all(string.as_bytes())
-            .or(Err("Could not write to file"))?;
+            .map_err(config_error("Could not write to file"))?;
 
         Ok(String::from("✓"))
     }
 
-    pub fn load(path: &str) -> Result<Config, String> {
+    pub fn load(path: &str) -> Result<Config, Error> {
         let mut json = String::new();
 
         fs::File::open(path)
-            .or(Err("Could not find file"))?
+            .map_err(config_error("Could not find file"))?
             .read_to_string(&mut json)
-            .or(Err("Could not read to string"))?;
+            .map_err(config_error("Could not read to string"))?;
+
+        serde_json::from_str::<Config>(&json)
+            .map_err(|err| Error::Config(format!("Could not parse JSON in {}: {}", path, err)))
+    }
 
-        serde_json::from_str::<Config>(&json).map_err(|_| String::from("Could not parse JSON"))
+    /// Name of a project in config by its id
+    pub fn project_name(&self, id: &str) -> Option<&String> {
+        self.projects
+            .iter()
+            .find(|(_, project)| project.id == id)
+            .map(|(name, _)| name)
     }
 
     pub fn set_path(self, path: &str) -> Config {
@@ -73,9 +178,9 @@ impl Config {
         }
     }
 
-    pub fn add_project(self, name: String, number: u32) -> Config {
+    pub fn add_project(self, name: String, project: Project) -> Config {
         let mut projects = self.projects;
-        projects.insert(name, number);
+        projects.insert(name, project);
 
         Config { projects, ..self }
     }
@@ -87,22 +192,66 @@ impl Config {
         Config { projects, ..self }
     }
 
-    pub fn set_next_id(&self, next_id: String) -> Config {
-        let next_id: Option<String> = Some(next_id);
+    pub fn set_listing(&self, listing: Vec<String>) -> Config {
+        Config {
+            listing,
+            ..self.clone()
+        }
+    }
+
+    /// Use the weights of a profile, or of scoring_profile when name is None.
+    /// The default weights are used when neither is set, or for a profile named default.
+    pub fn with_scoring_profile(self, name: Option<&str>) -> Result<Config, Error> {
+        let name = name
+            .map(String::from)
+            .or_else(|| self.scoring_profile.clone());
+        let scoring = match name {
+            None => Scoring::default(),
+            Some(name) => match self.scoring_profiles.get(&name) {
+                Some(scoring) => scoring.clone(),
+                None if name == "default" => Scoring::default(),
+                None => {
+                    return Err(Error::Config(format!(
+                        "Scoring profile {} not found in scoring_profiles",
+                        name
+                    )))
+                }
+            },
+        };
+        Ok(Config { scoring, ..self })
+    }
 
+    /// Replace the focus queue, the first task becomes the next task
+    pub fn set_focus(&self, focus: Vec<String>) -> Config {
         Config {
-            next_id,
+            next_id: focus.first().cloned(),
+            focus,
             ..self.clone()
         }
     }
 
-    pub fn clear_next_id(self) -> Config {
-        let next_id: Option<String> = None;
+    /// Remove tasks from the focus queue, moving on to the first remaining task
+    /// when the next task is one of them
+    pub fn remove_from_focus(&self, ids: &[String]) -> Config {
+        let focus: Vec<String> = self
+            .focus
+            .iter()
+            .filter(|id| !ids.contains(id))
+            .cloned()
+            .collect();
+        let next_id = match &self.next_id {
+            Some(id) if ids.contains(id) => focus.first().cloned(),
+            next_id => next_id.clone(),
+        };
 
-        Config { next_id, ..self }
+        Config {
+            next_id,
+            focus,
+            ..self.clone()
+        }
     }
 
-    fn check_for_latest_version(self: Config) -> Result<Config, String> {
+    fn check_for_latest_version(self: Config) -> Result<Config, Error> {
         let last_version = self.clone().last_version_check;
         let new_config = Config {
             last_version_check: Some(time::today_string(&self)),
@@ -110,9 +259,9 @@ impl Config {
         };
 
         if last_version != Some(time::today_string(&self)) {
-            match request::get_latest_version() {
+            match request::get_latest_version(&self) {
                 Ok(version) if version.as_str() != VERSION => {
-                    println!(
+                    eprintln!(
                         "Latest Tod version is {}, found {}.\nRun {} to update if you installed with Cargo",
                         version,
                         VERSION,
@@ -121,7 +270,7 @@ impl Config {
                     new_config.clone().save().unwrap();
                 }
                 Ok(_) => (),
-                Err(err) => println!(
+                Err(err) => eprintln!(
                     "{}, {:?}",
                     "Could not fetch Tod version from Cargo.io".red(),
                     err
@@ -132,13 +281,13 @@ impl Config {
         Ok(new_config)
     }
 
-    fn check_for_timezone(self: Config) -> Result<Config, String> {
+    fn check_for_timezone(self: Config) -> Result<Config, Error> {
         if self.timezone.is_none() {
             time::list_timezones();
             let desc = "Please enter the number of your timezone";
             let num: usize = get_input(desc)?
                 .parse::<usize>()
-                .map_err(|_| String::from("Could not parse string into number"))?;
+                .map_err(|_| Error::Input(String::from("Could not parse string into number")))?;
             let config = Config {
                 timezone: Some(time::get_timezone(num)),
                 ..self
@@ -153,7 +302,7 @@ impl Config {
     }
 }
 
-pub fn get_or_create(config_path: Option<&str>) -> Result<Config, String> {
+pub fn get_or_create(config_path: Option<&str>) -> Result<Config, Error> {
     let path: String = match config_path {
         None => generate_path()?,
         Some(path) => String::from(path).trim().to_owned(),
@@ -165,11 +314,11 @@ pub fn get_or_create(config_path: Option<&str>) -> Result<Config, String> {
         // This moves it to new path
         let legacy_path = generate_legacy_path()?;
         if path_exists(&legacy_path) {
-            println!(
+            eprintln!(
                 "INFO: Moving the config file from \"{}\" to \"{}\".\n",
                 legacy_path, path
             );
-            fs::rename(legacy_path, &path).map_err(|e| e.to_string())?;
+            fs::rename(legacy_path, &path).map_err(config_error("Could not move config file"))?;
         }
     }
 
@@ -199,34 +348,107 @@ fn path_exists(path: &str) -> bool {
     std::path::Path::new(path).exists()
 }
 
-pub fn generate_path() -> Result<String, String> {
+pub fn generate_path() -> Result<String, Error> {
     let filename = "tod.cfg";
 
     let config_directory = dirs::config_dir()
-        .ok_or_else(|| String::from("Could not find config directory"))?
+        .ok_or_else(|| Error::Config(String::from("Could not find config directory")))?
         .to_str()
-        .ok_or_else(|| String::from("Could not convert config directory to string"))?
+        .ok_or_else(|| Error::Config(String::from("Could not convert config directory to string")))?
         .to_owned();
     Ok(format!("{}/{}", config_directory, filename))
 }
 
-pub fn generate_legacy_path() -> Result<String, String> {
+pub fn generate_legacy_path() -> Result<String, Error> {
     let filename = ".tod.cfg";
 
     let home_directory = dirs::home_dir()
-        .ok_or_else(|| String::from("Could not find home directory"))?
+        .ok_or_else(|| Error::Config(String::from("Could not find home directory")))?
         .to_str()
-        .ok_or_else(|| String::from("Could not convert directory to string"))?
+        .ok_or_else(|| Error::Config(String::from("Could not convert directory to string")))?
         .to_owned();
     Ok(format!("{}/{}", home_directory, filename))
 }
 
-pub fn get_input(desc: &str) -> Result<String, String> {
+/// Wrap an io::Error with context as a config error
+fn config_error(context: &'static str) -> impl Fn(io::Error) -> Error {
+    move |err| Error::Config(format!("{}: {}", context, err))
+}
+
+pub fn get_input(desc: &str) -> Result<String, Error> {
     let mut input = String::new();
-    println!("{}", desc);
+    eprintln!("{}", desc);
     io::stdin()
         .read_line(&mut input)
-        .or(Err("error: unable to read user input"))?;
+        .or(Err(Error::Input(String::from(
+            "error: unable to read user input",
+        ))))?;
 
     Ok(String::from(input.trim()))
 }
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Config written to a temporary directory, with Todoist pointed at a mock server.
+    /// The directory and the files kept next to the config are removed when it is dropped.
+    pub fn mock_config(todoist_url: &str) -> (Config, TempDir) {
+        // The environment takes precedence over config, tests must only reach the mock server
+        std::env::remove_var("TOD_TODOIST_URL");
+        std::env::remove_var("TOD_CARGO_URL");
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("tod.cfg").to_str().unwrap().to_owned();
+
+        let config = Config {
+            path,
+            todoist_url: Some(String::from(todoist_url)),
+            cargo_url: Some(String::from(todoist_url)),
+            timezone: Some(String::from("UTC")),
+            last_version_check: None,
+            ..Config::new("alreadycreated").unwrap()
+        }
+        .add_project(String::from("inbox"), Project::new("1"))
+        .add_project(String::from("work"), Project::new("2"));
+
+        (config.create().unwrap(), dir)
+    }
+
+    #[test]
+    fn legacy_numeric_project_ids_load() {
+        let (config, _dir) = mock_config("http://localhost");
+        let json = json!(config)
+            .to_string()
+            .replace(r#"{"id":"1","parent_id":null,"color":null}"#, "1");
+        fs::write(&config.path, json).unwrap();
+
+        assert_eq!(Config::load(&config.path), Ok(config));
+    }
+
+    #[test]
+    fn optional_fields_can_be_left_out() {
+        let (config, _dir) = mock_config("http://localhost");
+        let mut json = json!(config);
+        for field in ["todoist_url", "cargo_url", "undo_minutes"] {
+            json.as_object_mut().unwrap().remove(field);
+        }
+        fs::write(&config.path, json.to_string()).unwrap();
+
+        let loaded = Config::load(&config.path).unwrap();
+        assert_eq!(loaded.todoist_url, None);
+        assert_eq!(loaded.undo_minutes, None);
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let (config, _dir) = mock_config("http://localhost");
+        let config = Config {
+            next_id: Some(String::from("5")),
+            ..config
+        };
+        config.clone().save().unwrap();
+
+        assert_eq!(Config::load(&config.path), Ok(config));
+    }
+}
@@ -0,0 +1,87 @@
+use std::fmt;
+
+/// Errors returned throughout Tod, each mapping to a distinct process exit code
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// No response from the server, i.e. offline or DNS failure
+    Network(String),
+    /// The server responded with a non-success status code
+    Http { status: u16, body: String },
+    /// A response or stored file could not be parsed
+    Parse(String),
+    /// The config file could not be found, read or written
+    Config(String),
+    /// Invalid arguments or interactive input from the user
+    Input(String),
+    /// Todoist rejected a Sync API command
+    Command(String),
+    /// The terminal could not be set up or read, i.e. for the TUI
+    Terminal(String),
+}
+
+impl Error {
+    /// Exit code for main, so that scripts can tell failures apart
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Input(_) => 2,
+            Error::Config(_) => 3,
+            Error::Parse(_) => 4,
+            Error::Network(_) => 5,
+            Error::Command(_) => 6,
+            Error::Terminal(_) => 7,
+            Error::Http { status, .. } => match status {
+                401 | 403 => 10,
+                404 => 11,
+                429 => 12,
+                500..=599 => 13,
+                _ => 14,
+            },
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Network(message) => write!(f, "Network error: {}", message),
+            Error::Http { status, body } => {
+                let reason = match status {
+                    401 | 403 => "check your API token",
+                    404 => "not found",
+                    429 => "rate limited, try again later",
+                    500..=599 => "server error",
+                    _ => "request failed",
+                };
+                write!(f, "HTTP {} ({}): {}", status, reason, body)
+            }
+            Error::Parse(message) => write!(f, "Parse error: {}", message),
+            Error::Config(message) => write!(f, "Config error: {}", message),
+            Error::Input(message) => write!(f, "{}", message),
+            Error::Command(message) => write!(f, "Todoist rejected the change: {}", message),
+            Error::Terminal(message) => write!(f, "Terminal error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn http_statuses_have_distinct_exit_codes() {
+        let http = |status| Error::Http {
+            status,
+            body: String::new(),
+        };
+
+        assert_eq!(http(401).exit_code(), http(403).exit_code());
+        assert_ne!(http(401).exit_code(), http(503).exit_code());
+        assert_ne!(http(404).exit_code(), http(429).exit_code());
+        assert_ne!(
+            Error::Network(String::new()).exit_code(),
+            http(500).exit_code()
+        );
+    }
+}
@@ -0,0 +1,298 @@
+use chrono::Duration;
+use std::collections::HashSet;
+
+use crate::cache::Cache;
+use crate::config::Config;
+use crate::error::Error;
+use crate::items::Item;
+use crate::{projects, time};
+
+/// A parsed Todoist filter query, i.e. "(today | overdue) & #Work & !@waiting"
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Filter {
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+    Today,
+    Tomorrow,
+    Overdue,
+    NoDate,
+    NoLabels,
+    Recurring,
+    /// Due from today up to the given number of days, i.e. "7 days"
+    Days(i64),
+    /// Priority as stored by the API, p1 is 4
+    Priority(u8),
+    /// Ids of the project, and of its subprojects with ##
+    Project(HashSet<String>),
+    Label(String),
+    Section(String),
+    Search(String),
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Token {
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+    Term(String),
+}
+
+impl Filter {
+    /// Parse a query, resolving project names with the projects in config
+    pub fn parse(query: &str, config: &Config) -> Result<Filter, Error> {
+        let tokens = tokenize(query);
+        let mut parser = Parser {
+            tokens: &tokens,
+            position: 0,
+            config,
+        };
+        let filter = parser.or()?;
+        match parser.peek() {
+            None => Ok(filter),
+            Some(token) => Err(filter_error(&format!("unexpected {:?}", token))),
+        }
+    }
+
+    pub fn matches(&self, item: &Item, config: &Config, cache: &Cache) -> bool {
+        let due_date = item.due_date(config);
+        let today = time::today_date(config);
+        match self {
+            Filter::And(left, right) => {
+                left.matches(item, config, cache) && right.matches(item, config, cache)
+            }
+            Filter::Or(left, right) => {
+                left.matches(item, config, cache) || right.matches(item, config, cache)
+            }
+            Filter::Not(filter) => !filter.matches(item, config, cache),
+            Filter::Today => due_date == Some(today),
+            Filter::Tomorrow => due_date == Some(today + Duration::days(1)),
+            Filter::Overdue => item.is_overdue(config),
+            Filter::NoDate => item.due.is_none(),
+            Filter::NoLabels => item.labels.is_empty(),
+            Filter::Recurring => item.due.as_ref().is_some_and(|due| due.is_recurring),
+            Filter::Days(days) => {
+                due_date.is_some_and(|date| date >= today && date < today + Duration::days(*days))
+            }
+            Filter::Priority(priority) => item.priority == *priority,
+            Filter::Project(ids) => ids.contains(&item.project_id),
+            Filter::Label(label) => item.labels.iter().any(|l| l.to_lowercase() == *label),
+            Filter::Section(name) => cache
+                .sections
+                .iter()
+                .filter(|section| section.name.to_lowercase() == *name)
+                .any(|section| item.section_id.as_ref() == Some(&section.id)),
+            Filter::Search(text) => item.content.to_lowercase().contains(text),
+        }
+    }
+}
+
+/// Split a query into operators and terms, "," is treated as "|"
+fn tokenize(query: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut term = String::new();
+    for character in query.chars() {
+        let token = match character {
+            '&' => Token::And,
+            '|' | ',' => Token::Or,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '!' if term.trim().is_empty() => Token::Not,
+            _ => {
+                term.push(character);
+                continue;
+            }
+        };
+        push_term(&mut tokens, &mut term);
+        tokens.push(token);
+    }
+    push_term(&mut tokens, &mut term);
+    tokens
+}
+
+fn push_term(tokens: &mut Vec<Token>, term: &mut String) {
+    let trimmed = term.trim();
+    if !trimmed.is_empty() {
+        tokens.push(Token::Term(String::from(trimmed)));
+    }
+    term.clear();
+}
+
+/// Recursive descent parser, & binds tighter than |
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    config: &'a Config,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Filter, Error> {
+        let mut filter = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            filter = Filter::Or(Box::new(filter), Box::new(self.and()?));
+        }
+        Ok(filter)
+    }
+
+    fn and(&mut self) -> Result<Filter, Error> {
+        let mut filter = self.unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            filter = Filter::And(Box::new(filter), Box::new(self.unary()?));
+        }
+        Ok(filter)
+    }
+
+    fn unary(&mut self) -> Result<Filter, Error> {
+        match self.next() {
+            Some(Token::Not) => Ok(Filter::Not(Box::new(self.unary()?))),
+            Some(Token::Open) => {
+                let filter = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(filter),
+                    _ => Err(filter_error("missing )")),
+                }
+            }
+            Some(Token::Term(term)) => term_filter(&term, self.config),
+            Some(token) => Err(filter_error(&format!("unexpected {:?}", token))),
+            None => Err(filter_error("unexpected end of query")),
+        }
+    }
+}
+
+fn term_filter(term: &str, config: &Config) -> Result<Filter, Error> {
+    let lowercase = term.to_lowercase();
+    if let Some(name) = term.strip_prefix("##") {
+        return Ok(Filter::Project(project_ids(config, name, true)?));
+    }
+    if let Some(name) = term.strip_prefix('#') {
+        return Ok(Filter::Project(project_ids(config, name, false)?));
+    }
+    if let Some(label) = lowercase.strip_prefix('@') {
+        return Ok(Filter::Label(String::from(label)));
+    }
+    if let Some(section) = lowercase.strip_prefix('/') {
+        return Ok(Filter::Section(String::from(section)));
+    }
+    if let Some(text) = lowercase.strip_prefix("search:") {
+        return Ok(Filter::Search(String::from(text.trim())));
+    }
+
+    match lowercase.as_str() {
+        "today" => Ok(Filter::Today),
+        "tomorrow" => Ok(Filter::Tomorrow),
+        "overdue" | "od" => Ok(Filter::Overdue),
+        "no date" => Ok(Filter::NoDate),
+        "no labels" => Ok(Filter::NoLabels),
+        "recurring" => Ok(Filter::Recurring),
+        "p1" => Ok(Filter::Priority(4)),
+        "p2" => Ok(Filter::Priority(3)),
+        "p3" => Ok(Filter::Priority(2)),
+        "p4" => Ok(Filter::Priority(1)),
+        _ => {
+            let days = lowercase
+                .trim_start_matches("next ")
+                .strip_suffix(" days")
+                .and_then(|days| days.trim().parse::<i64>().ok());
+            match days {
+                Some(days) => Ok(Filter::Days(days)),
+                None => Err(filter_error(&format!("unknown term {}", term))),
+            }
+        }
+    }
+}
+
+/// Id of a project, with the ids of all of its subprojects when nested is true
+fn project_ids(config: &Config, name: &str, nested: bool) -> Result<HashSet<String>, Error> {
+    let mut ids = HashSet::from([projects::project_id(config, name.trim())?]);
+    if !nested {
+        return Ok(ids);
+    }
+
+    loop {
+        let children: Vec<String> = config
+            .projects
+            .values()
+            .filter(|project| {
+                project
+                    .parent_id
+                    .as_ref()
+                    .is_some_and(|id| ids.contains(id))
+            })
+            .map(|project| project.id.clone())
+            .filter(|id| !ids.contains(id))
+            .collect();
+        if children.is_empty() {
+            break;
+        }
+        ids.extend(children);
+    }
+    Ok(ids)
+}
+
+fn filter_error(message: &str) -> Error {
+    Error::Input(format!("Could not parse filter, {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::tests::mock_config;
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let (config, _dir) = mock_config("http://localhost");
+
+        assert_eq!(
+            Filter::parse("overdue | today & !p1", &config),
+            Ok(Filter::Or(
+                Box::new(Filter::Overdue),
+                Box::new(Filter::And(
+                    Box::new(Filter::Today),
+                    Box::new(Filter::Not(Box::new(Filter::Priority(4))))
+                ))
+            ))
+        );
+        assert_eq!(
+            Filter::parse("(#work | @Waiting) & 7 days", &config),
+            Ok(Filter::And(
+                Box::new(Filter::Or(
+                    Box::new(Filter::Project(HashSet::from([String::from("2")]))),
+                    Box::new(Filter::Label(String::from("waiting")))
+                )),
+                Box::new(Filter::Days(7))
+            ))
+        );
+    }
+
+    #[test]
+    fn invalid_queries() {
+        let (config, _dir) = mock_config("http://localhost");
+
+        assert_eq!(
+            Filter::parse("(today", &config),
+            Err(Error::Input(String::from(
+                "Could not parse filter, missing )"
+            )))
+        );
+        assert_eq!(
+            Filter::parse("someday", &config),
+            Err(Error::Input(String::from(
+                "Could not parse filter, unknown term someday"
+            )))
+        );
+    }
+}
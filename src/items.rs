@@ -4,31 +4,116 @@ use chrono_tz::Tz;
 use colored::*;
 use serde::{Deserialize, Serialize};
 use std::cmp::Reverse;
+use std::collections::HashSet;
 
+use crate::commands::Command;
 use crate::config::Config;
-use crate::{config, items, request, time};
+use crate::error::Error;
+use crate::output::ItemRecord;
+use crate::scoring::Score;
+use crate::{config, time};
 
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 pub struct Item {
     pub id: String,
+    #[serde(default)]
+    pub project_id: String,
     pub content: String,
     pub priority: u8,
     pub checked: bool,
     pub description: String,
     pub due: Option<DateInfo>,
     pub is_deleted: bool,
+    #[serde(default)]
+    pub labels: Vec<String>,
+    #[serde(default)]
+    pub parent_id: Option<String>,
+    #[serde(default)]
+    pub child_order: i64,
+    #[serde(default)]
+    pub section_id: Option<String>,
+    #[serde(default)]
+    pub duration: Option<DurationInfo>,
+    #[serde(default)]
+    pub deadline: Option<Deadline>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Eq, PartialEq)]
 pub struct DateInfo {
     pub date: String,
     pub is_recurring: bool,
     pub timezone: Option<String>,
+    /// The date as written by the user, i.e. "every monday"
+    #[serde(default)]
+    pub string: String,
+    /// Language of string, needed to parse it again
+    #[serde(default)]
+    pub lang: Option<String>,
+}
+
+/// How long a task takes, i.e. 30 minutes or 2 days
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct DurationInfo {
+    pub amount: u32,
+    /// "minute" or "day"
+    pub unit: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct Body {
-    items: Vec<Item>,
+/// The date a task has to be done by, unlike the due date it is not when to work on it
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct Deadline {
+    pub date: String,
+    #[serde(default)]
+    pub lang: Option<String>,
+}
+
+impl DurationInfo {
+    /// Parse 30m, 1h30m, 90 in minutes, or 2d in days
+    pub fn parse(input: &str) -> Option<DurationInfo> {
+        let input = input.trim().to_lowercase();
+        if let Some(days) = input.strip_suffix('d') {
+            return days
+                .trim()
+                .parse::<u32>()
+                .ok()
+                .filter(|days| *days > 0)
+                .map(|amount| DurationInfo {
+                    amount,
+                    unit: String::from("day"),
+                });
+        }
+
+        let (hours, minutes) = match input.split_once('h') {
+            Some((hours, minutes)) => (hours.trim().parse::<u32>().ok()?, minutes),
+            None => (0, input.as_str()),
+        };
+        let minutes = match minutes.trim().trim_end_matches('m') {
+            "" => 0,
+            minutes => minutes.trim().parse::<u32>().ok()?,
+        };
+        let amount = hours * 60 + minutes;
+        (amount > 0).then(|| DurationInfo {
+            amount,
+            unit: String::from("minute"),
+        })
+    }
+
+    pub fn minutes(&self) -> i64 {
+        match self.unit.as_str() {
+            "day" => self.amount as i64 * 24 * 60,
+            _ => self.amount as i64,
+        }
+    }
+
+    /// i.e. 1h 30m, or 2 days
+    pub fn fmt(&self) -> String {
+        match (self.unit.as_str(), self.amount) {
+            ("day", 1) => String::from("1 day"),
+            ("day", days) => format!("{} days", days),
+            (_, minutes) if minutes < 60 => format!("{}m", minutes),
+            (_, minutes) => format!("{}h {:02}m", minutes / 60, minutes % 60),
+        }
+    }
 }
 
 enum DateTimeInfo {
@@ -52,69 +137,146 @@ impl Item {
             _ => self.content.normal(),
         };
 
+        let labels = match self.labels.as_slice() {
+            [] => String::new(),
+            labels => format!(" {}", format_labels(labels).cyan()),
+        };
+
         let description = match &*self.description {
             "" => String::from(""),
             _ => format!("\n{}", self.description),
         };
 
-        let due = match &self.datetimeinfo(config) {
-            Ok(DateTimeInfo::Date { date, is_recurring }) => {
-                let recurring_icon = if *is_recurring { " ↻" } else { "" };
-                let date_string = time::format_date(date, config);
+        let due = match self.fmt_due(config) {
+            Ok(Some(due)) => format!("\nDue: {}", due),
+            Ok(None) => String::from(""),
+            Err(err) => format!("\n{}", err),
+        };
 
-                format!("\nDue: {}{}", date_string, recurring_icon)
-            }
-            Ok(DateTimeInfo::DateTime {
-                datetime,
-                is_recurring,
-            }) => {
-                let recurring_icon = if *is_recurring { " ↻" } else { "" };
-                let datetime_string = time::format_datetime(datetime, config);
+        let deadline = match self.deadline_date() {
+            Some(date) if time::is_date_in_past(date, config) => format!(
+                "\n{}",
+                format!("Deadline: {}", time::format_date(&date, config)).red()
+            ),
+            Some(date) => format!("\nDeadline: {}", time::format_date(&date, config)),
+            None => String::new(),
+        };
+
+        format!("\n{}{}{}{}{}", content, labels, description, due, deadline)
+    }
+
+    fn deadline_date(&self) -> Option<NaiveDate> {
+        let deadline = self.deadline.as_ref()?;
+        NaiveDate::parse_from_str(&deadline.date, "%Y-%m-%d").ok()
+    }
+
+    /// Start and end of an item with a time and a duration in minutes
+    pub fn time_block(&self, config: &Config) -> Option<(DateTime<Tz>, DateTime<Tz>)> {
+        let duration = self.duration.as_ref().filter(|d| d.unit == "minute")?;
+        let start = self.datetime(config)?;
+        Some((start, start + chrono::Duration::minutes(duration.minutes())))
+    }
 
-                format!("\nDue: {}{}", datetime_string, recurring_icon)
+    /// The due date or time, followed by the recurrence of recurring items,
+    /// i.e. "Today ↻ every weekday"
+    pub fn fmt_due(&self, config: &Config) -> Result<Option<String>, Error> {
+        let date_string = match self.datetimeinfo(config)? {
+            DateTimeInfo::NoDateTime => return Ok(None),
+            DateTimeInfo::Date { date, .. } => time::format_date(&date, config),
+            DateTimeInfo::DateTime { datetime, .. } => time::format_datetime(&datetime, config),
+        };
+        let date_string = match (self.time_block(config), &self.duration) {
+            (Some((_, end)), _) => {
+                let tz = time::timezone_from_str(&config.timezone);
+                format!("{}–{}", date_string, end.with_timezone(&tz).format("%H:%M"))
+            }
+            (None, Some(duration)) => format!("{} for {}", date_string, duration.fmt()),
+            (None, None) => date_string,
+        };
+        let recurrence = match &self.due {
+            Some(due) if due.is_recurring && !due.string.is_empty() => {
+                format!(" ↻ {}", due.string)
             }
-            Ok(DateTimeInfo::NoDateTime) => String::from(""),
-            Err(string) => string.clone(),
+            Some(due) if due.is_recurring => String::from(" ↻"),
+            _ => String::new(),
         };
 
-        format!("\n{}{}{}", content, description, due)
+        Ok(Some(format!("{}{}", date_string, recurrence)))
+    }
+
+    /// The item with computed fields, for json and csv output
+    pub fn record(&self, config: &Config) -> ItemRecord {
+        ItemRecord {
+            id: self.id.clone(),
+            project_id: self.project_id.clone(),
+            parent_id: self.parent_id.clone(),
+            section_id: self.section_id.clone(),
+            content: self.content.clone(),
+            description: self.description.clone(),
+            priority: self.priority,
+            labels: self.labels.clone(),
+            due: self.due.as_ref().map(|due| due.date.clone()),
+            is_recurring: self
+                .due
+                .as_ref()
+                .map(|due| due.is_recurring)
+                .unwrap_or(false),
+            has_time: self.has_time(config),
+            is_today: self.is_today(config),
+            is_overdue: self.is_overdue(config),
+            sort_value: self.value(config),
+            duration_minutes: self.duration.as_ref().map(DurationInfo::minutes),
+            deadline: self.deadline.as_ref().map(|deadline| deadline.date.clone()),
+        }
     }
 
     /// Determines the numeric value of an item for sorting
     fn value(&self, config: &Config) -> u32 {
-        let date_value: u8 = self.date_value(config);
-        let priority_value: u8 = self.priority_value();
-
-        date_value as u32 + priority_value as u32
+        self.score(config).total()
     }
 
-    /// Return the value of the due field
-    fn date_value(&self, config: &Config) -> u8 {
+    /// Points for the due date and priority, weighted by the scoring profile in config
+    pub fn score(&self, config: &Config) -> Score {
+        let scoring = &config.scoring;
+        let mut score = Score::default();
+        let recurring = |score: &mut Score, is_recurring: bool| {
+            if !is_recurring {
+                score.add("not recurring", scoring.not_recurring);
+            }
+        };
+
         match &self.datetimeinfo(config) {
-            Ok(DateTimeInfo::NoDateTime) => 80,
+            Ok(DateTimeInfo::NoDateTime) => score.add("no date", scoring.no_date),
             Ok(DateTimeInfo::Date { date, is_recurring }) => {
-                let today_value = if *date == time::today_date(config) {
-                    100
-                } else {
-                    0
-                };
-                let overdue_value = if self.is_overdue(config) { 150 } else { 0 };
-                let recurring_value = if is_recurring.to_owned() { 0 } else { 50 };
-                today_value + overdue_value + recurring_value
+                if *date == time::today_date(config) {
+                    score.add("due today", scoring.due_today);
+                }
+                if self.is_overdue(config) {
+                    score.add("overdue", scoring.overdue);
+                }
+                recurring(&mut score, *is_recurring);
             }
             Ok(DateTimeInfo::DateTime {
                 datetime,
                 is_recurring,
             }) => {
-                let recurring_value = if is_recurring.to_owned() { 0 } else { 50 };
-                let duration = *datetime - time::now(config);
-                match duration.num_minutes() {
-                    -15..=15 => 200 + recurring_value,
-                    _ => recurring_value,
+                let minutes = (*datetime - time::now(config)).num_minutes();
+                if minutes.abs() <= scoring.due_soon_minutes {
+                    score.add("due soon", scoring.due_soon);
                 }
+                recurring(&mut score, *is_recurring);
             }
-            Err(_) => 50,
+            Err(_) => score.add("invalid date", scoring.invalid_date),
         }
+
+        let priority = match self.priority {
+            4 => "p1",
+            3 => "p2",
+            2 => "p3",
+            _ => "p4",
+        };
+        score.add(priority, scoring.priority(self.priority));
+        score
     }
 
     /// Return the value of the due field
@@ -125,17 +287,8 @@ impl Item {
         }
     }
 
-    fn priority_value(&self) -> u8 {
-        match self.priority {
-            2 => 1,
-            3 => 3,
-            4 => 4,
-            _ => 2,
-        }
-    }
-
     /// Converts the JSON date representation into Date or Datetime
-    fn datetimeinfo(&self, config: &Config) -> Result<DateTimeInfo, String> {
+    fn datetimeinfo(&self, config: &Config) -> Result<DateTimeInfo, Error> {
         let tz = match (self.clone().due, config.clone().timezone) {
             (None, Some(tz_string)) => time::timezone_from_str(&Some(tz_string)),
             (None, None) => Tz::UTC,
@@ -164,6 +317,15 @@ impl Item {
         }
     }
 
+    /// The day the item is due, in the timezone of the item
+    pub fn due_date(&self, config: &Config) -> Option<NaiveDate> {
+        match self.datetimeinfo(config) {
+            Ok(DateTimeInfo::Date { date, .. }) => Some(date),
+            Ok(DateTimeInfo::DateTime { datetime, .. }) => Some(datetime.date_naive()),
+            _ => None,
+        }
+    }
+
     fn has_no_date(&self) -> bool {
         self.due.is_none()
     }
@@ -180,7 +342,7 @@ impl Item {
         }
     }
 
-    fn is_overdue(&self, config: &Config) -> bool {
+    pub fn is_overdue(&self, config: &Config) -> bool {
         match self.clone().datetimeinfo(config) {
             Ok(DateTimeInfo::NoDateTime) => false,
             Ok(DateTimeInfo::Date { date, .. }) => time::is_date_in_past(date, config),
@@ -200,18 +362,88 @@ impl Item {
     }
 }
 
-pub fn json_to_items(json: String) -> Result<Vec<Item>, String> {
-    let result: Result<Body, _> = serde_json::from_str(&json);
-    match result {
-        Ok(body) => Ok(body.items),
-        Err(err) => Err(format!("Could not parse response for item: {:?}", err)),
+pub fn json_to_item(json: String) -> Result<Item, Error> {
+    match serde_json::from_str(&json) {
+        Ok(item) => Ok(item),
+        Err(err) => Err(Error::Parse(format!(
+            "Could not parse response for item: {:?}",
+            err
+        ))),
     }
 }
 
-pub fn json_to_item(json: String) -> Result<Item, String> {
-    match serde_json::from_str(&json) {
-        Ok(item) => Ok(item),
-        Err(err) => Err(format!("Could not parse response for item: {:?}", err)),
+/// Labels as they are written in Todoist, i.e. "@errands @phone"
+pub fn format_labels(labels: &[String]) -> String {
+    labels
+        .iter()
+        .map(|label| format!("@{}", label))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Keep items that have the label, ignoring case and a leading @
+pub fn filter_by_label(items: Vec<Item>, label: Option<&str>) -> Vec<Item> {
+    match label {
+        None => items,
+        Some(label) => {
+            let label = label.trim_start_matches('@').to_lowercase();
+            items
+                .into_iter()
+                .filter(|item| item.labels.iter().any(|l| l.to_lowercase() == label))
+                .collect()
+        }
+    }
+}
+
+/// Keep items in the section, or all items when there is none
+pub fn filter_by_section(items: Vec<Item>, section_id: Option<&str>) -> Vec<Item> {
+    match section_id {
+        None => items,
+        Some(section_id) => items
+            .into_iter()
+            .filter(|item| item.section_id.as_deref() == Some(section_id))
+            .collect(),
+    }
+}
+
+/// Remove parents that still have open subtasks in items, leaving the tasks that can be done now
+pub fn filter_leaves(items: Vec<Item>) -> Vec<Item> {
+    let parents: HashSet<String> = items
+        .iter()
+        .filter_map(|item| item.parent_id.clone())
+        .collect();
+    items
+        .into_iter()
+        .filter(|item| !parents.contains(&item.id))
+        .collect()
+}
+
+/// Items with subtasks after their parent in child_order, paired with their depth.
+/// Top level items keep their order, as do subtasks whose parent is not in items.
+pub fn tree(items: Vec<Item>) -> Vec<(usize, Item)> {
+    let ids: HashSet<String> = items.iter().map(|item| item.id.clone()).collect();
+    let (roots, mut children): (Vec<Item>, Vec<Item>) =
+        items.into_iter().partition(|item| match &item.parent_id {
+            Some(parent_id) => !ids.contains(parent_id),
+            None => true,
+        });
+    children.sort_by_key(|item| item.child_order);
+
+    let mut tree = Vec::new();
+    for root in roots {
+        push_subtree(&mut tree, root, 0, &children);
+    }
+    tree
+}
+
+fn push_subtree(tree: &mut Vec<(usize, Item)>, item: Item, depth: usize, children: &[Item]) {
+    let id = item.id.clone();
+    tree.push((depth, item));
+    for child in children
+        .iter()
+        .filter(|child| child.parent_id.as_ref() == Some(&id))
+    {
+        push_subtree(tree, child.clone(), depth + 1, children);
     }
 }
 
@@ -225,7 +457,25 @@ pub fn sort_by_datetime(mut items: Vec<Item>, config: &Config) -> Vec<Item> {
     items
 }
 
-pub fn filter_not_in_future(items: Vec<Item>, config: &Config) -> Result<Vec<Item>, String> {
+/// Pairs of items whose time blocks overlap, in the order of the items
+pub fn overlaps<'a>(items: &'a [Item], config: &Config) -> Vec<(&'a Item, &'a Item)> {
+    let blocks: Vec<_> = items
+        .iter()
+        .filter_map(|item| item.time_block(config).map(|block| (item, block)))
+        .collect();
+
+    let mut overlaps = Vec::new();
+    for (index, (first, (start, end))) in blocks.iter().enumerate() {
+        for (second, (other_start, other_end)) in blocks.iter().skip(index + 1) {
+            if start < other_end && other_start < end {
+                overlaps.push((*first, *second));
+            }
+        }
+    }
+    overlaps
+}
+
+pub fn filter_not_in_future(items: Vec<Item>, config: &Config) -> Result<Vec<Item>, Error> {
     let items = items
         .into_iter()
         .filter(|item| item.is_today(config) || item.has_no_date() || item.is_overdue(config))
@@ -241,25 +491,19 @@ pub fn filter_today_and_has_time(items: Vec<Item>, config: &Config) -> Vec<Item>
         .collect()
 }
 
-pub fn set_priority(config: Config, item: items::Item) {
-    println!("{}", item.fmt(&config));
+/// Ask for a priority, returning the update command or None when the input is not valid
+pub fn set_priority(config: &Config, item: &Item) -> Result<Option<Command>, Error> {
+    println!("{}", item.fmt(config));
 
-    let priority = config::get_input("Choose a priority from 1 (lowest) to 3 (highest):")
-        .expect("Please enter a number from 1 to 3");
+    let priority = config::get_input("Choose a priority from 1 (lowest) to 3 (highest):")?;
 
     match priority.as_str() {
-        "1" => {
-            let config = config.set_next_id(item.id.clone());
-            request::update_item_priority(config, item, 2).expect("could not set priority");
-        }
-        "2" => {
-            let config = config.set_next_id(item.id.clone());
-            request::update_item_priority(config, item, 3).expect("could not set priority");
-        }
-        "3" => {
-            let config = config.set_next_id(item.id.clone());
-            request::update_item_priority(config, item, 4).expect("could not set priority");
+        "1" => Ok(Some(Command::item_update_priority(&item.id, 2))),
+        "2" => Ok(Some(Command::item_update_priority(&item.id, 3))),
+        "3" => Ok(Some(Command::item_update_priority(&item.id, 4))),
+        _ => {
+            println!("Not a valid input, please enter 1, 2, or 3");
+            Ok(None)
         }
-        _ => println!("Not a valid input, please enter 1, 2, or 3"),
     }
 }
@@ -0,0 +1,193 @@
+use colored::*;
+use serde::Deserialize;
+
+use crate::config::Config;
+use crate::error::Error;
+use crate::{request, time};
+
+/// Width of a goal progress bar
+const PROGRESS_WIDTH: usize = 20;
+
+#[derive(Deserialize)]
+struct KarmaResponse {
+    user: User,
+    #[serde(default)]
+    stats: Stats,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct User {
+    karma: f64,
+    karma_trend: String,
+    daily_goal: u32,
+    weekly_goal: u32,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Stats {
+    completed_count: u32,
+    days_items: Vec<DayItems>,
+    week_items: Vec<WeekItems>,
+}
+
+#[derive(Deserialize)]
+struct DayItems {
+    date: String,
+    total_completed: u32,
+}
+
+#[derive(Deserialize)]
+struct WeekItems {
+    from: String,
+    to: String,
+    total_completed: u32,
+}
+
+impl KarmaResponse {
+    fn completed_today(&self, config: &Config) -> u32 {
+        let today = time::today_string(config);
+        self.stats
+            .days_items
+            .iter()
+            .find(|day| day.date == today)
+            .map(|day| day.total_completed)
+            .unwrap_or(0)
+    }
+
+    fn completed_this_week(&self, config: &Config) -> u32 {
+        let today = time::today_string(config);
+        self.stats
+            .week_items
+            .iter()
+            .find(|week| week.from <= today && today <= week.to)
+            .map(|week| week.total_completed)
+            .unwrap_or(0)
+    }
+}
+
+fn fetch(config: &Config) -> Result<KarmaResponse, Error> {
+    let json = request::get_user_stats(config)?;
+    serde_json::from_str(&json)
+        .map_err(|err| Error::Parse(format!("Could not parse karma response: {:?}", err)))
+}
+
+/// Karma and its trend, with progress towards the daily and weekly goals
+pub fn karma(config: &Config) -> Result<String, Error> {
+    let response = fetch(config)?;
+    let trend = match response.user.karma_trend.as_str() {
+        "up" => "↑".green(),
+        "down" => "↓".red(),
+        _ => "".normal(),
+    };
+
+    let mut buffer = format!("Karma {} {}", response.user.karma, trend)
+        .trim_end()
+        .green()
+        .to_string();
+    buffer.push_str(&format!(
+        "\nToday      {}",
+        progress(response.completed_today(config), response.user.daily_goal)
+    ));
+    buffer.push_str(&format!(
+        "\nThis week  {}",
+        progress(
+            response.completed_this_week(config),
+            response.user.weekly_goal
+        )
+    ));
+    buffer.push_str(&format!("\nCompleted  {}", response.stats.completed_count));
+    Ok(buffer)
+}
+
+/// Progress towards today's goal, shown after completing tasks.
+/// None when it cannot be fetched, so that completing never fails because of it.
+pub fn daily_goal_progress(config: &Config) -> Option<String> {
+    let response = match fetch(config) {
+        Ok(response) => response,
+        Err(err) => {
+            eprintln!(
+                "{}",
+                format!("Could not fetch goal progress: {}", err).yellow()
+            );
+            return None;
+        }
+    };
+    let done = response.completed_today(config);
+    let goal = response.user.daily_goal;
+    match goal {
+        0 => None,
+        goal if done >= goal => Some(
+            format!("Daily goal reached ({}/{})", done, goal)
+                .green()
+                .to_string(),
+        ),
+        goal => Some(format!("Daily goal {}", progress(done, goal))),
+    }
+}
+
+/// i.e. ██████░░░░ 3/5
+fn progress(done: u32, goal: u32) -> String {
+    if goal == 0 {
+        return format!("{} (no goal set)", done);
+    }
+
+    let filled = (done.min(goal) as usize * PROGRESS_WIDTH) / goal as usize;
+    format!(
+        "{}{} {}/{}",
+        "█".repeat(filled).cyan(),
+        "░".repeat(PROGRESS_WIDTH - filled),
+        done,
+        goal
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::tests::mock_config;
+    use serde_json::json;
+
+    #[test]
+    fn karma_shows_goals() {
+        let mut server = mockito::Server::new();
+        let (config, _dir) = mock_config(&server.url());
+        let today = time::today_string(&config);
+        server
+            .mock("POST", "/sync/v9/sync")
+            .match_body(mockito::Matcher::PartialJson(
+                json!({"resource_types": ["user", "stats"]}),
+            ))
+            .with_body(
+                json!({
+                    "user": {"karma": 5120.0, "karma_trend": "up", "daily_goal": 4, "weekly_goal": 20},
+                    "stats": {
+                        "completed_count": 812,
+                        "days_items": [{"date": today, "total_completed": 2}],
+                        "week_items": [{"from": "2000-01-01", "to": "2999-12-31", "total_completed": 25}],
+                    },
+                })
+                .to_string(),
+            )
+            .create();
+
+        assert_eq!(
+            karma(&config).unwrap(),
+            format!(
+                "Karma 5120 ↑\nToday      {}{} 2/4\nThis week  {} 25/20\nCompleted  812",
+                "█".repeat(10),
+                "░".repeat(10),
+                "█".repeat(20)
+            )
+        );
+        assert_eq!(
+            daily_goal_progress(&config),
+            Some(format!(
+                "Daily goal {}{} 2/4",
+                "█".repeat(10),
+                "░".repeat(10)
+            ))
+        );
+    }
+}
@@ -2,350 +2,762 @@ extern crate matches;
 
 extern crate clap;
 
+use chrono::NaiveDate;
 use clap::{Arg, ArgAction, ArgMatches, Command};
+use clap_complete::Shell;
 use colored::*;
+use commands::Changes;
+use error::Error;
+use items::DurationInfo;
+use std::io::IsTerminal;
 
+mod cache;
+mod commands;
+mod completed;
 mod config;
+mod error;
+mod filter;
 mod items;
+mod karma;
+mod output;
 mod projects;
+mod queue;
 mod request;
+mod scoring;
 mod time;
+mod timer;
+mod tui;
+mod undo;
 
 const APP: &str = "todo";
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
-struct Arguments<'a> {
-    new_task: Option<String>,
-    config_path: Option<&'a str>,
-    project: Option<&'a str>,
-    next_task: bool,
-    complete_task: bool,
-    list_projects: bool,
-    add_project: Option<Vec<String>>,
-    remove_project: Option<&'a str>,
-    sort_inbox: bool,
-    prioritize_tasks: bool,
-    scheduled_items: bool,
+fn main() {
+    let matches = cmd().get_matches();
+
+    match dispatch(&matches) {
+        Ok(text) => {
+            println!("{}", text);
+            std::process::exit(0);
+        }
+        Err(e) => {
+            eprintln!("{}", e.to_string().red());
+            std::process::exit(e.exit_code());
+        }
+    }
 }
 
-fn main() {
-    let app = Command::new(APP);
-    let matches = app
+fn cmd() -> Command {
+    Command::new(APP)
+        .version(VERSION)
+        .about("A Todoist client for the terminal")
+        .subcommand_required(true)
+        .arg_required_else_help(true)
         .arg(
-            Arg::new("new task")
-                .short('t')
-                .long("task")
-                .required(false)
-                .action(ArgAction::Append)
-                .num_args(1..)
-                .value_parser(clap::value_parser!(String))
-                .help(
-                    "Create a new task with text. Can specify project option, defaults to inbox.",
-                ),
+            Arg::new("configuration path")
+                .short('o')
+                .long("config")
+                .num_args(1)
+                .global(true)
+                .value_name("CONFIGURATION PATH")
+                .help("Absolute path of configuration. Defaults to $XDG_CONFIG_HOME/tod.cfg"),
         )
         .arg(
-            Arg::new("project")
-                .short('p')
-                .long("project")
-                .required(false)
-                .value_name("PROJECT NAME")
-                .help("The project namespace, for filtering other commands, use by itself to list all tasks for the project"),
+            Arg::new("output")
+                .long("output")
+                .num_args(1)
+                .global(true)
+                .value_parser(output::FORMATS)
+                .default_value("plain")
+                .value_name("FORMAT")
+                .help("Output format for listing commands. Colors are disabled unless plain output goes to a terminal"),
         )
         .arg(
-            flag_arg("next task", 'n', "next", "Get the next task by priority. Requires project option.")
-                    )
-        .arg(
-            flag_arg("complete task", 'c', "complete", "Complete the last task fetched with next")
-                            )
-        .arg(
-            flag_arg("list projects", 'l', "list", "List all projects in the local config."),
+            Arg::new("profile")
+                .long("profile")
+                .num_args(1)
+                .global(true)
+                .value_name("PROFILE")
+                .help("Scoring profile from scoring_profiles in config, used to choose and sort tasks"),
         )
-        .arg(
-            Arg::new("add project")
-                .short('a')
-                .long("add")
-                .required(false)
-                .action(ArgAction::Append)
-                .num_args(2)
-                .value_parser(clap::value_parser!(String))
-                .value_names(["PROJECT NAME", "PROJECT ID"])
-                .help("Add a project to config with id"),
+        .subcommand(
+            Command::new("add")
+                .visible_alias("a")
+                .about("Create a new task with natural language, i.e. \"Call mum tomorrow at 5pm\"")
+                .arg(
+                    Arg::new("text")
+                        .required(true)
+                        .action(ArgAction::Append)
+                        .num_args(1..)
+                        .value_name("TEXT"),
+                )
+                .arg(project_arg())
+                .arg(duration_arg())
+                .arg(deadline_arg()),
         )
-        .arg(
-            Arg::new("remove project")
-                .short('r')
-                .long("remove")
-                .num_args(1)
-                .required(false)
-                .value_name("PROJECT NAME")
-                .help("Remove a project from config by name"),
+        .subcommand(
+            Command::new("next")
+                .visible_alias("n")
+                .about("Get the next task by priority")
+                .arg(project_arg())
+                .arg(label_arg())
+                .arg(section_arg())
+                .arg(
+                    Arg::new("count")
+                        .short('n')
+                        .long("count")
+                        .value_parser(clap::builder::RangedU64ValueParser::<usize>::new().range(1..))
+                        .default_value("1")
+                        .value_name("N")
+                        .help("Number of tasks to show, all candidates are queued for complete and skip"),
+                )
+                .arg(
+                    Arg::new("explain")
+                        .long("explain")
+                        .action(ArgAction::SetTrue)
+                        .help("Show the score of each candidate task"),
+                )
+                .arg(
+                    Arg::new("start")
+                        .long("start")
+                        .action(ArgAction::SetTrue)
+                        .help("Start tracking time on the next task"),
+                )
+                .arg(pomodoro_arg()),
         )
-        .arg(
-            flag_arg("sort inbox", 's', "sort", "Sort inbox by moving tasks into projects")
+        .subcommand(
+            Command::new("complete")
+                .visible_alias("c")
+                .about("Complete tasks by id or number in the last list, defaults to the last task fetched with next")
+                .arg(tasks_arg(false)),
         )
-        .arg(
-            flag_arg("prioritize tasks", 'z', "prioritize", "Assign priorities to tasks. Can specify project option, defaults to inbox.")
+        .subcommand(
+            Command::new("start")
+                .about("Track time on a task, defaults to the last task fetched with next")
+                .arg(
+                    Arg::new("task")
+                        .value_name("TASK")
+                        .help("Task id, or number from the last list or schedule"),
+                )
+                .arg(pomodoro_arg()),
         )
-        .arg(
-            flag_arg("scheduled items", 'e', "scheduled", "Returns items that are today and have a time. Can specify project option, defaults to inbox.")
+        .subcommand(Command::new("stop").about("Stop tracking time"))
+        .subcommand(Command::new("status").about("Show the task being tracked and time elapsed"))
+        .subcommand(
+            Command::new("report")
+                .about("Show time tracked per project and task")
+                .arg(
+                    Arg::new("days")
+                        .long("days")
+                        .value_parser(clap::value_parser!(i64).range(1..))
+                        .value_name("DAYS")
+                        .help("Only sessions started in the last days"),
+                ),
         )
-        .arg(
-            Arg::new("configuration path")
-                .short('o')
-                .long("config")
-                .num_args(1)
-                .required(false)
-                .value_name("CONFIGURATION PATH")
-                .help("Absolute path of configuration. Defaults to $XDG_CONFIG_HOME/tod.cfg"),
+        .subcommand(
+            Command::new("completed")
+                .about("List completed tasks, from the last 7 days by default")
+                .arg(
+                    Arg::new("project")
+                        .short('p')
+                        .long("project")
+                        .num_args(1)
+                        .value_name("PROJECT NAME")
+                        .help("Only tasks completed in this project"),
+                )
+                .arg(date_arg("since", "First day to include, i.e. 2024-03-01"))
+                .arg(date_arg("until", "Last day to include")),
         )
-        .get_matches();
-
-    let new_task = matches
-        .get_many("new task")
-        .map(|values| values.cloned().collect::<Vec<String>>().join(" "));
-    let add_project = matches
-        .get_many("add project")
-        .map(|values| values.cloned().collect::<Vec<String>>());
-
-    let arguments = Arguments {
-        new_task,
-        project: matches.get_one::<String>("project").map(|s| s.as_str()),
-        next_task: has_flag(matches.clone(), "next task"),
-        complete_task: has_flag(matches.clone(), "complete task"),
-        list_projects: has_flag(matches.clone(), "list projects"),
-        sort_inbox: has_flag(matches.clone(), "sort inbox"),
-        add_project,
-        remove_project: matches
-            .get_one::<String>("remove project")
-            .map(|s| s.as_str()),
-        config_path: matches
-            .get_one::<String>("configuration path")
-            .map(|s| s.as_str()),
-        prioritize_tasks: has_flag(matches.clone(), "prioritize tasks"),
-        scheduled_items: has_flag(matches.clone(), "scheduled items"),
-    };
-
-    match dispatch(arguments) {
-        Ok(text) => {
-            println!("{}", text);
-            std::process::exit(0);
+        .subcommand(
+            Command::new("stats")
+                .about("Show completed tasks per day, week and project, and streaks")
+                .arg(
+                    Arg::new("days")
+                        .long("days")
+                        .value_parser(clap::value_parser!(i64).range(1..))
+                        .default_value("28")
+                        .value_name("DAYS")
+                        .help("Number of days of history, ending today"),
+                ),
+        )
+        .subcommand(
+            Command::new("karma").about("Show karma, its trend and progress towards your goals"),
+        )
+        .subcommand(
+            Command::new("skip")
+                .about("Defer the task fetched with next and show the following one in the queue"),
+        )
+        .subcommand(
+            Command::new("uncomplete")
+                .visible_alias("reopen")
+                .about("Reopen completed tasks by id or number in the last list")
+                .arg(tasks_arg(true)),
+        )
+        .subcommand(
+            Command::new("modify")
+                .visible_alias("m")
+                .about("Change the content, description, due date, labels or priority of a task")
+                .arg(
+                    Arg::new("task")
+                        .required(true)
+                        .value_name("TASK")
+                        .help("Task id, or number from the last list or schedule"),
+                )
+                .arg(Arg::new("content").long("content").num_args(1).value_name("TEXT"))
+                .arg(
+                    Arg::new("description")
+                        .short('d')
+                        .long("description")
+                        .num_args(1)
+                        .value_name("TEXT"),
+                )
+                .arg(
+                    Arg::new("due")
+                        .long("due")
+                        .num_args(1)
+                        .value_name("DATE")
+                        .help("Natural language, i.e. \"every friday at 4pm\" or \"no date\""),
+                )
+                .arg(
+                    Arg::new("label")
+                        .short('l')
+                        .long("label")
+                        .action(ArgAction::Append)
+                        .num_args(1)
+                        .value_name("LABEL")
+                        .help("Replaces all labels, repeat for more than one"),
+                )
+                .arg(priority_arg())
+                .arg(duration_arg())
+                .arg(deadline_arg()),
+        )
+        .subcommand(
+            Command::new("reschedule")
+                .about("Move a task to another date, recurring tasks keep recurring")
+                .arg(
+                    Arg::new("task")
+                        .required(true)
+                        .value_name("TASK")
+                        .help("Task id, or number from the last list or schedule"),
+                )
+                .arg(
+                    Arg::new("date")
+                        .required(true)
+                        .num_args(1..)
+                        .value_name("DATE")
+                        .help("Natural language, i.e. \"friday at 5pm\", \"next week\" or 2024-03-01"),
+                ),
+        )
+        .subcommand(
+            Command::new("postpone")
+                .about("Push a task back by days, from today when it is overdue")
+                .arg(
+                    Arg::new("task")
+                        .required(true)
+                        .value_name("TASK")
+                        .help("Task id, or number from the last list or schedule"),
+                )
+                .arg(
+                    Arg::new("days")
+                        .value_parser(clap::value_parser!(i64).range(1..))
+                        .default_value("1")
+                        .value_name("DAYS"),
+                ),
+        )
+        .subcommand(
+            Command::new("triage-overdue")
+                .about("Go through overdue tasks in all projects and reschedule or complete them"),
+        )
+        .subcommand(
+            Command::new("delete")
+                .visible_alias("d")
+                .about("Delete tasks by id or number in the last list, can be undone for a while")
+                .arg(tasks_arg(true))
+                .arg(
+                    Arg::new("yes")
+                        .short('y')
+                        .long("yes")
+                        .action(ArgAction::SetTrue)
+                        .help("Do not ask for confirmation"),
+                ),
+        )
+        .subcommand(Command::new("undo").about("Recreate the tasks removed by the last delete"))
+        .subcommand(
+            Command::new("list")
+                .visible_alias("l")
+                .about("List all tasks in a project")
+                .arg(project_arg())
+                .arg(label_arg())
+                .arg(section_arg())
+                .arg(
+                    Arg::new("namespace")
+                        .long("namespace")
+                        .action(ArgAction::SetTrue)
+                        .help("Show subtasks as parent > child instead of indented"),
+                ),
+        )
+        .subcommand(
+            Command::new("projects")
+                .visible_alias("p")
+                .about("Manage the projects in config")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .subcommand(
+                    Command::new("list")
+                        .visible_alias("l")
+                        .about("List all projects in config"),
+                )
+                .subcommand(
+                    Command::new("add")
+                        .visible_alias("a")
+                        .about("Add a project to config with its id, see also projects sync")
+                        .arg(Arg::new("name").required(true).value_name("PROJECT NAME"))
+                        .arg(Arg::new("id").required(true).value_name("PROJECT ID")),
+                )
+                .subcommand(
+                    Command::new("remove")
+                        .visible_alias("r")
+                        .about("Remove a project from config by name")
+                        .arg(Arg::new("name").required(true).value_name("PROJECT NAME")),
+                )
+                .subcommand(
+                    Command::new("sync")
+                        .visible_alias("s")
+                        .about("Fetch all projects from Todoist and store them in config"),
+                ),
+        )
+        .subcommand(
+            Command::new("labels")
+                .about("List labels, or add and remove them on tasks")
+                .subcommand(
+                    Command::new("add")
+                        .visible_alias("a")
+                        .about("Add labels to a task, keeping its other labels")
+                        .arg(
+                            Arg::new("task")
+                                .required(true)
+                                .value_name("TASK")
+                                .help("Task id, or number from the last list or schedule"),
+                        )
+                        .arg(labels_arg()),
+                )
+                .subcommand(
+                    Command::new("remove")
+                        .visible_alias("r")
+                        .about("Remove labels from a task")
+                        .arg(
+                            Arg::new("task")
+                                .required(true)
+                                .value_name("TASK")
+                                .help("Task id, or number from the last list or schedule"),
+                        )
+                        .arg(labels_arg()),
+                ),
+        )
+        .subcommand(
+            Command::new("today")
+                .about("Show overdue tasks and tasks due today in all projects"),
+        )
+        .subcommand(
+            Command::new("upcoming")
+                .about("Show overdue tasks and tasks due in the coming days in all projects")
+                .arg(
+                    Arg::new("days")
+                        .value_parser(clap::value_parser!(i64).range(1..))
+                        .default_value("7")
+                        .value_name("DAYS")
+                        .help("Number of days to show, starting today"),
+                ),
+        )
+        .subcommand(
+            Command::new("filter")
+                .visible_alias("f")
+                .about("List tasks in all projects that match a Todoist filter")
+                .arg(
+                    Arg::new("query")
+                        .required(true)
+                        .value_name("QUERY")
+                        .help("i.e. \"today & p1\", \"overdue | #Work\" or \"@waiting\""),
+                ),
+        )
+        .subcommand(
+            Command::new("sort")
+                .visible_alias("s")
+                .about("Sort inbox by moving tasks into projects"),
+        )
+        .subcommand(
+            Command::new("prioritize")
+                .visible_alias("z")
+                .about("Assign priorities to tasks without one")
+                .arg(project_arg()),
+        )
+        .subcommand(
+            Command::new("schedule")
+                .visible_alias("e")
+                .about("List tasks that are today and have a time")
+                .arg(project_arg()),
+        )
+        .subcommand(
+            Command::new("pending")
+                .visible_alias("q")
+                .about("List changes queued while offline, waiting to be sent to Todoist"),
+        )
+        .subcommand(
+            Command::new("tui")
+                .visible_alias("t")
+                .about("Open the full screen interface"),
+        )
+        .subcommand(
+            Command::new("completions")
+                .about("Print a shell completion script")
+                .arg(
+                    Arg::new("shell")
+                        .required(true)
+                        .value_parser(clap::value_parser!(Shell))
+                        .value_name("SHELL"),
+                ),
+        )
+}
+
+fn dispatch(matches: &ArgMatches) -> Result<String, Error> {
+    // Completions should work before a config exists
+    if let Some(("completions", args)) = matches.subcommand() {
+        let shell = *args
+            .get_one::<Shell>("shell")
+            .ok_or_else(|| Error::Input(String::from("Shell is required")))?;
+        return completions(shell);
+    }
+
+    let config_path = matches
+        .get_one::<String>("configuration path")
+        .map(|s| s.as_str());
+    let format = output::Format::from_name(
+        matches
+            .get_one::<String>("output")
+            .map(|s| s.as_str())
+            .unwrap_or("plain"),
+    );
+    if format != output::Format::Plain || !std::io::stdout().is_terminal() {
+        colored::control::set_override(false);
+    }
+    let profile = matches.get_one::<String>("profile").map(|s| s.as_str());
+    let config = config::Config {
+        output: format,
+        ..config::get_or_create(config_path)?
+    }
+    .with_scoring_profile(profile)?;
+
+    match matches.subcommand() {
+        Some(("add", args)) => {
+            let task = args
+                .get_many::<String>("text")
+                .map(|values| values.cloned().collect::<Vec<String>>().join(" "))
+                .unwrap_or_default();
+            let changes = Changes {
+                duration: args.get_one::<Option<DurationInfo>>("duration").cloned(),
+                deadline: args.get_one::<Option<NaiveDate>>("deadline").copied(),
+                ..Changes::default()
+            };
+            projects::add_item_to_project(config, &task, project(args), &changes)
         }
-        Err(e) => {
-            println!("{}", e.red());
-            std::process::exit(1);
+        Some(("next", args)) => {
+            let next = projects::next_item(
+                config.clone(),
+                project(args),
+                label(args),
+                section(args),
+                *args.get_one::<usize>("count").unwrap_or(&1),
+                args.get_flag("explain"),
+            )?;
+            if !args.get_flag("start") && pomodoro(args).is_none() {
+                return Ok(next);
+            }
+            if config.output != output::Format::Plain {
+                // Keep json and csv output parseable, the timer status goes to stderr
+                let started =
+                    timer::start(&config::Config::load(&config.path)?, None, pomodoro(args))?;
+                eprintln!("{}", started);
+                return Ok(next);
+            }
+            println!("{}", next);
+            timer::start(&config::Config::load(&config.path)?, None, pomodoro(args))
+        }
+        Some(("start", args)) => timer::start(
+            &config,
+            args.get_one::<String>("task").map(|s| s.as_str()),
+            pomodoro(args),
+        ),
+        Some(("stop", _)) => timer::stop(&config),
+        Some(("status", _)) => timer::status(&config),
+        Some(("report", args)) => timer::report(&config, args.get_one::<i64>("days").copied()),
+        Some(("completed", args)) => completed::completed(
+            &config,
+            args.get_one::<String>("project").map(|s| s.as_str()),
+            args.get_one::<NaiveDate>("since").copied(),
+            args.get_one::<NaiveDate>("until").copied(),
+        ),
+        Some(("stats", args)) => completed::stats(
+            &config,
+            *args
+                .get_one::<i64>("days")
+                .unwrap_or(&completed::DEFAULT_STATS_DAYS),
+        ),
+        Some(("karma", _)) => karma::karma(&config),
+        Some(("skip", _)) => projects::skip(config),
+        Some(("complete", args)) => projects::complete_items(config, &tasks(args)),
+        Some(("uncomplete", args)) => projects::uncomplete_items(config, &tasks(args)),
+        Some(("modify", args)) => {
+            let changes = Changes {
+                content: args.get_one::<String>("content").cloned(),
+                description: args.get_one::<String>("description").cloned(),
+                due_string: args.get_one::<String>("due").cloned(),
+                labels: args
+                    .get_many::<String>("label")
+                    .map(|values| values.cloned().collect()),
+                priority: priority(args),
+                duration: args.get_one::<Option<DurationInfo>>("duration").cloned(),
+                deadline: args.get_one::<Option<NaiveDate>>("deadline").copied(),
+            };
+            let task = args.get_one::<String>("task").cloned().unwrap_or_default();
+            projects::modify_item(config, &task, &changes)
+        }
+        Some(("reschedule", args)) => {
+            let task = args.get_one::<String>("task").cloned().unwrap_or_default();
+            let date: Vec<String> = args
+                .get_many::<String>("date")
+                .map(|values| values.cloned().collect())
+                .unwrap_or_default();
+            projects::reschedule(&config, &task, &date.join(" "))
         }
+        Some(("postpone", args)) => {
+            let task = args.get_one::<String>("task").cloned().unwrap_or_default();
+            projects::postpone(&config, &task, *args.get_one::<i64>("days").unwrap_or(&1))
+        }
+        Some(("triage-overdue", _)) => projects::triage_overdue(&config),
+        Some(("delete", args)) => {
+            let ids = projects::resolve_ids(&config, &tasks(args))?;
+            undo::delete_items(&config, &ids, args.get_flag("yes"))
+        }
+        Some(("undo", _)) => undo::undo(&config),
+        Some(("list", args)) => projects::all_items(
+            &config,
+            project(args),
+            label(args),
+            section(args),
+            args.get_flag("namespace"),
+        ),
+        Some(("labels", args)) => match args.subcommand() {
+            None => projects::labels(&config),
+            Some((action, args)) => {
+                let task: Vec<String> = args
+                    .get_one::<String>("task")
+                    .into_iter()
+                    .cloned()
+                    .collect();
+                let labels: Vec<String> = args
+                    .get_many::<String>("labels")
+                    .map(|values| values.cloned().collect())
+                    .unwrap_or_default();
+                projects::update_labels(&config, &task, &labels, action == "remove")
+            }
+        },
+        Some(("projects", args)) => match args.subcommand() {
+            Some(("list", _)) => projects::list(config),
+            Some(("add", args)) => {
+                let params = ["name", "id"]
+                    .iter()
+                    .filter_map(|id| args.get_one::<String>(id).cloned())
+                    .collect();
+                projects::add(config, params)
+            }
+            Some(("remove", args)) => projects::remove(
+                config,
+                args.get_one::<String>("name").unwrap_or(&String::new()),
+            ),
+            Some(("sync", _)) => projects::sync_projects(config),
+            _ => Err(invalid_parameters()),
+        },
+        Some(("today", _)) => projects::agenda(&config, 1),
+        Some(("upcoming", args)) => {
+            projects::agenda(&config, *args.get_one::<i64>("days").unwrap_or(&7))
+        }
+        Some(("filter", args)) => match args.get_one::<String>("query") {
+            Some(query) => projects::filter_items(&config, query),
+            None => Err(invalid_parameters()),
+        },
+        Some(("sort", _)) => projects::sort_inbox(config),
+        Some(("prioritize", args)) => projects::prioritize_items(&config, project(args)),
+        Some(("schedule", args)) => projects::scheduled_items(&config, project(args)),
+        Some(("pending", _)) => queue::pending(&config),
+        Some(("tui", _)) => tui::run(config),
+        _ => Err(invalid_parameters()),
     }
 }
 
-fn dispatch(arguments: Arguments) -> Result<String, String> {
-    let config: config::Config = config::get_or_create(arguments.config_path)?;
-
-    match arguments {
-        Arguments {
-            new_task: Some(task),
-            project: Some(project),
-            next_task: false,
-            complete_task: false,
-            list_projects: false,
-            add_project: None,
-            remove_project: None,
-            sort_inbox: false,
-            prioritize_tasks: false,
-            scheduled_items: false,
-            config_path: _,
-        } => projects::add_item_to_project(config, &task, project),
-        Arguments {
-            new_task: Some(task),
-            project: None,
-            next_task: false,
-            complete_task: false,
-            list_projects: false,
-            add_project: None,
-            remove_project: None,
-            sort_inbox: false,
-            prioritize_tasks: false,
-            scheduled_items: false,
-            config_path: _,
-        } => projects::add_item_to_project(config, &task, "inbox"),
-        Arguments {
-            new_task: None,
-            project: Some(project),
-            next_task: true,
-            complete_task: false,
-            list_projects: false,
-            add_project: None,
-            remove_project: None,
-            sort_inbox: false,
-            prioritize_tasks: false,
-            scheduled_items: false,
-            config_path: _,
-        } => projects::next_item(config, project),
-        Arguments {
-            new_task: None,
-            project: None,
-            next_task: false,
-            complete_task: true,
-            list_projects: false,
-            add_project: None,
-            remove_project: None,
-            sort_inbox: false,
-            prioritize_tasks: false,
-            scheduled_items: false,
-            config_path: _,
-        } => match request::complete_item(config) {
-            Ok(_) => Ok(String::from("✓")),
-            Err(err) => Err(err),
-        },
-        Arguments {
-            new_task: None,
-            project: None,
-            next_task: false,
-            complete_task: false,
-            list_projects: true,
-            add_project: None,
-            remove_project: None,
-            sort_inbox: false,
-            prioritize_tasks: false,
-            scheduled_items: false,
-            config_path: _,
-        } => projects::list(config),
-        Arguments {
-            new_task: None,
-            project: None,
-            next_task: false,
-            complete_task: false,
-            list_projects: false,
-            add_project: Some(params),
-            remove_project: None,
-            sort_inbox: false,
-            prioritize_tasks: false,
-            scheduled_items: false,
-            config_path: _,
-        } => projects::add(config, params),
-        Arguments {
-            new_task: None,
-            project: None,
-            next_task: false,
-            complete_task: false,
-            list_projects: false,
-            add_project: None,
-            remove_project: Some(project_name),
-            sort_inbox: false,
-            prioritize_tasks: false,
-            scheduled_items: false,
-            config_path: _,
-        } => projects::remove(config, project_name),
-        Arguments {
-            new_task: None,
-            project: None,
-            next_task: false,
-            complete_task: false,
-            list_projects: false,
-            add_project: None,
-            remove_project: None,
-            sort_inbox: true,
-            prioritize_tasks: false,
-            scheduled_items: false,
-            config_path: _,
-        } => projects::sort_inbox(config),
-        Arguments {
-            new_task: None,
-            project: None,
-            next_task: false,
-            complete_task: false,
-            list_projects: false,
-            add_project: None,
-            remove_project: None,
-            sort_inbox: false,
-            prioritize_tasks: true,
-            scheduled_items: false,
-            config_path: _,
-        } => projects::prioritize_items(&config, "inbox"),
-        Arguments {
-            new_task: None,
-            project: Some(project_name),
-            next_task: false,
-            complete_task: false,
-            list_projects: false,
-            add_project: None,
-            remove_project: None,
-            sort_inbox: false,
-            prioritize_tasks: true,
-            scheduled_items: false,
-            config_path: _,
-        } => projects::prioritize_items(&config, project_name),
-        Arguments {
-            new_task: None,
-            project: None,
-            next_task: false,
-            complete_task: false,
-            list_projects: false,
-            add_project: None,
-            remove_project: None,
-            sort_inbox: false,
-            prioritize_tasks: false,
-            scheduled_items: true,
-            config_path: _,
-        } => projects::scheduled_items(&config, "inbox"),
-        Arguments {
-            new_task: None,
-            project: Some(project_name),
-            next_task: false,
-            complete_task: false,
-            list_projects: false,
-            add_project: None,
-            remove_project: None,
-            sort_inbox: false,
-            prioritize_tasks: false,
-            scheduled_items: true,
-            config_path: _,
-        } => projects::scheduled_items(&config, project_name),
-        Arguments {
-            new_task: None,
-            project: Some(project_name),
-            next_task: false,
-            complete_task: false,
-            list_projects: false,
-            add_project: None,
-            remove_project: None,
-            sort_inbox: false,
-            prioritize_tasks: false,
-            scheduled_items: false,
-            config_path: _,
-        } => projects::all_items(&config, project_name),
-        Arguments {
-            new_task: None,
-            project: None,
-            next_task: false,
-            complete_task: false,
-            list_projects: false,
-            add_project: None,
-            remove_project: None,
-            sort_inbox: false,
-            prioritize_tasks: false,
-            scheduled_items: false,
-            config_path: _,
-        } => Err(String::from(
-            "Tod cannot be run without parameters. To see available parameters use --help",
-        )),
-        _ => Err(String::from(
-            "Invalid parameters. For more information try --help",
-        )),
-    }
+fn tasks_arg(required: bool) -> Arg {
+    Arg::new("tasks")
+        .required(required)
+        .action(ArgAction::Append)
+        .num_args(1..)
+        .value_name("TASK")
+        .help("Task id, or number from the last list or schedule")
 }
 
-fn flag_arg(id: &'static str, short: char, long: &'static str, help: &'static str) -> Arg {
+fn tasks(args: &ArgMatches) -> Vec<String> {
+    args.get_many::<String>("tasks")
+        .map(|values| values.cloned().collect())
+        .unwrap_or_default()
+}
+
+fn priority_arg() -> Arg {
+    Arg::new("priority")
+        .long("priority")
+        .num_args(1)
+        .value_parser(["1", "2", "3", "4"])
+        .value_name("PRIORITY")
+        .help("1 is the highest, as p1 in Todoist")
+}
+
+/// Convert p1 to p4 into the API priority, where 4 is the highest
+fn priority(args: &ArgMatches) -> Option<u8> {
+    args.get_one::<String>("priority")
+        .and_then(|p| p.parse::<u8>().ok())
+        .map(|p| 5 - p)
+}
+
+fn date_arg(id: &'static str, help: &'static str) -> Arg {
     Arg::new(id)
-        .short(short)
-        .long(long)
-        .value_parser(["yes", "no"])
-        .num_args(0..1)
-        .default_value("no")
-        .default_missing_value("yes")
-        .required(false)
+        .long(id)
+        .num_args(1)
+        .value_parser(|date: &str| NaiveDate::parse_from_str(date, "%Y-%m-%d"))
+        .value_name("YYYY-MM-DD")
         .help(help)
 }
 
-fn has_flag(matches: ArgMatches, id: &'static str) -> bool {
-    matches.get_one::<String>(id) == Some(&String::from("yes"))
+fn duration_arg() -> Arg {
+    Arg::new("duration")
+        .long("duration")
+        .num_args(1)
+        .value_parser(|input: &str| match input {
+            "none" => Ok(None),
+            _ => DurationInfo::parse(input)
+                .map(Some)
+                .ok_or("use i.e. 30m, 1h30m or 2d, or none to remove it"),
+        })
+        .value_name("DURATION")
+        .help("How long the task takes, i.e. 30m, 1h30m or 2d, or none")
+}
+
+fn deadline_arg() -> Arg {
+    Arg::new("deadline")
+        .long("deadline")
+        .num_args(1)
+        .value_parser(|input: &str| match input {
+            "none" => Ok(None),
+            _ => NaiveDate::parse_from_str(input, "%Y-%m-%d").map(Some),
+        })
+        .value_name("YYYY-MM-DD")
+        .help("Date the task has to be done by, or none")
+}
+
+fn pomodoro_arg() -> Arg {
+    Arg::new("pomodoro")
+        .long("pomodoro")
+        .num_args(0..=1)
+        .value_parser(clap::value_parser!(u32))
+        .default_missing_value("0")
+        .value_name("MINUTES")
+        .help("Work in a pomodoro and get notified when it is over, defaults to pomodoro_minutes in config or 25")
+}
+
+/// Minutes of the pomodoro, 0 for the configured length
+fn pomodoro(args: &ArgMatches) -> Option<u32> {
+    args.get_one::<u32>("pomodoro").copied()
+}
+
+fn label_arg() -> Arg {
+    Arg::new("label")
+        .short('l')
+        .long("label")
+        .num_args(1)
+        .value_name("LABEL")
+        .help("Only tasks with this label")
+}
+
+fn label(args: &ArgMatches) -> Option<&str> {
+    args.get_one::<String>("label").map(|s| s.as_str())
+}
+
+fn section_arg() -> Arg {
+    Arg::new("section")
+        .short('s')
+        .long("section")
+        .num_args(1)
+        .value_name("SECTION NAME")
+        .help("Only tasks in this section of the project")
+}
+
+fn section(args: &ArgMatches) -> Option<&str> {
+    args.get_one::<String>("section").map(|s| s.as_str())
+}
+
+fn labels_arg() -> Arg {
+    Arg::new("labels")
+        .required(true)
+        .action(ArgAction::Append)
+        .num_args(1..)
+        .value_name("LABEL")
+}
+
+fn project_arg() -> Arg {
+    Arg::new("project")
+        .short('p')
+        .long("project")
+        .num_args(1)
+        .default_value("inbox")
+        .value_name("PROJECT NAME")
+        .help("The project name, in any case or a unique prefix")
+}
+
+fn project(args: &ArgMatches) -> &str {
+    args.get_one::<String>("project")
+        .map(|s| s.as_str())
+        .unwrap_or("inbox")
+}
+
+fn completions(shell: Shell) -> Result<String, Error> {
+    let mut buffer: Vec<u8> = Vec::new();
+    clap_complete::generate(shell, &mut cmd(), APP, &mut buffer);
+    String::from_utf8(buffer)
+        .map_err(|_| Error::Parse(String::from("Could not generate completions")))
+}
+
+fn invalid_parameters() -> Error {
+    Error::Input(String::from(
+        "Invalid parameters. For more information try --help",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verify_cmd() {
+        cmd().debug_assert();
+    }
+
+    #[test]
+    fn subcommand_aliases() {
+        let matches = cmd().get_matches_from(["todo", "n", "-p", "work"]);
+
+        assert!(matches!(matches.subcommand(), Some(("next", args)) if project(args) == "work"));
+    }
+
+    #[test]
+    fn next_count_must_be_positive() {
+        assert!(cmd()
+            .try_get_matches_from(["todo", "next", "--count", "0"])
+            .is_err());
+    }
 }
@@ -0,0 +1,168 @@
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::completed::CompletedItem;
+use crate::config::Config;
+use crate::error::Error;
+use crate::items::Item;
+
+/// How listing commands print their results
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Format {
+    /// Human readable text, colored when printing to a terminal
+    #[default]
+    Plain,
+    Json,
+    Csv,
+}
+
+pub const FORMATS: [&str; 3] = ["plain", "json", "csv"];
+
+/// Columns of an item record, in the order they are printed as CSV
+const ITEM_COLUMNS: [&str; 16] = [
+    "id",
+    "project_id",
+    "parent_id",
+    "section_id",
+    "content",
+    "description",
+    "priority",
+    "labels",
+    "due",
+    "is_recurring",
+    "has_time",
+    "is_today",
+    "is_overdue",
+    "sort_value",
+    "duration_minutes",
+    "deadline",
+];
+
+const COMPLETED_COLUMNS: [&str; 4] = ["task_id", "project_id", "content", "completed_at"];
+
+const PROJECT_COLUMNS: [&str; 4] = ["name", "id", "parent_id", "color"];
+
+/// An item with the fields computed by Tod, the stable schema for json and csv output
+#[derive(Serialize)]
+pub struct ItemRecord {
+    pub id: String,
+    pub project_id: String,
+    pub parent_id: Option<String>,
+    pub section_id: Option<String>,
+    pub content: String,
+    pub description: String,
+    pub priority: u8,
+    pub labels: Vec<String>,
+    pub due: Option<String>,
+    pub is_recurring: bool,
+    pub has_time: bool,
+    pub is_today: bool,
+    pub is_overdue: bool,
+    pub sort_value: u32,
+    pub duration_minutes: Option<i64>,
+    pub deadline: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ProjectRecord<'a> {
+    name: &'a str,
+    id: &'a str,
+    parent_id: Option<&'a str>,
+    color: Option<&'a str>,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Format {
+        match name {
+            "json" => Format::Json,
+            "csv" => Format::Csv,
+            _ => Format::Plain,
+        }
+    }
+}
+
+/// Items as a json array or csv table
+pub fn items(config: &Config, items: &[Item]) -> Result<String, Error> {
+    let records: Vec<ItemRecord> = items.iter().map(|item| item.record(config)).collect();
+    render(config.output, &records, &ITEM_COLUMNS)
+}
+
+/// Completed tasks as a json array or csv table
+pub fn completed(config: &Config, items: &[CompletedItem]) -> Result<String, Error> {
+    render(config.output, items, &COMPLETED_COLUMNS)
+}
+
+/// Projects in config as a json array or csv table, sorted by name
+pub fn projects(config: &Config) -> Result<String, Error> {
+    let mut records: Vec<ProjectRecord> = config
+        .projects
+        .iter()
+        .map(|(name, project)| ProjectRecord {
+            name,
+            id: &project.id,
+            parent_id: project.parent_id.as_deref(),
+            color: project.color.as_deref(),
+        })
+        .collect();
+    records.sort_by_key(|record| record.name);
+    render(config.output, &records, &PROJECT_COLUMNS)
+}
+
+fn render<T: Serialize>(format: Format, records: &[T], columns: &[&str]) -> Result<String, Error> {
+    let values = serde_json::to_value(records)
+        .map_err(|err| Error::Parse(format!("Could not convert to JSON: {}", err)))?;
+
+    match (format, values) {
+        (Format::Csv, Value::Array(rows)) => Ok(csv(&rows, columns)),
+        (_, values) => serde_json::to_string_pretty(&values)
+            .map_err(|err| Error::Parse(format!("Could not convert to JSON: {}", err))),
+    }
+}
+
+fn csv(rows: &[Value], columns: &[&str]) -> String {
+    let mut lines = vec![columns.join(",")];
+    for row in rows {
+        let fields: Vec<String> = columns
+            .iter()
+            .map(|column| match row.get(column) {
+                None | Some(Value::Null) => String::new(),
+                Some(Value::String(string)) => csv_field(string),
+                Some(Value::Array(values)) => csv_field(
+                    &values
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .collect::<Vec<&str>>()
+                        .join(" "),
+                ),
+                Some(value) => csv_field(&value.to_string()),
+            })
+            .collect();
+        lines.push(fields.join(","));
+    }
+    lines.join("\n")
+}
+
+/// Quote a field when it contains a separator, quote or newline
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        String::from(field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn csv_quotes_fields() {
+        let rows = vec![json!({"name": "Call \"Bob\", then Alice", "id": "1", "parent_id": null})];
+
+        assert_eq!(
+            csv(&rows, &["name", "id", "parent_id"]),
+            "name,id,parent_id\n\"Call \"\"Bob\"\", then Alice\",1,"
+        );
+    }
+}
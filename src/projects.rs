@@ -1,84 +1,389 @@
+use crate::cache::Cache;
+use crate::commands::{Changes, Command, Status, SyncResponse};
 use crate::config::Config;
-use crate::items::Item;
-use crate::{config, items, projects, request};
+use crate::error::Error;
+use crate::filter::Filter;
+use crate::items::{DateInfo, Item};
+use crate::output::Format;
+use crate::{cache, config, items, karma, output, projects, queue, request, time};
+use chrono::{Duration, NaiveDate};
 use colored::*;
+use std::collections::HashMap;
 
-const ADD_ERROR: &str = "Must provide project name and number, i.e. tod --add projectname 12345";
+/// Task ids are longer than this, shorter numbers can only refer to a listing
+const MIN_ID_LENGTH: usize = 6;
 
-/// List the projects in config
-pub fn list(config: Config) -> Result<String, String> {
-    let mut projects: Vec<String> = config.projects.keys().map(|k| k.to_owned()).collect();
-    if projects.is_empty() {
+const ADD_ERROR: &str =
+    "Must provide project name and id, i.e. todo projects add projectname 12345";
+
+/// List the projects in config, with subprojects indented under their parents
+pub fn list(config: Config) -> Result<String, Error> {
+    if config.output != Format::Plain {
+        return output::projects(&config);
+    }
+    if config.projects.is_empty() {
         return Ok(String::from("No projects found"));
     }
-    projects.sort();
     let mut buffer = String::new();
     buffer.push_str(&green_string("Projects"));
 
-    for key in projects {
-        buffer.push_str("\n - ");
-        buffer.push_str(&key);
+    for name in child_names(&config, None) {
+        push_project(&config, &mut buffer, &name, 1);
     }
     Ok(buffer)
 }
 
+/// Names of the projects whose parent is parent_id, sorted. Projects whose parent
+/// is not in config are listed at the top level.
+fn child_names(config: &Config, parent_id: Option<&str>) -> Vec<String> {
+    let ids: Vec<&String> = config.projects.values().map(|p| &p.id).collect();
+    let mut names: Vec<String> = config
+        .projects
+        .iter()
+        .filter(|(_, project)| {
+            let parent = project
+                .parent_id
+                .as_ref()
+                .filter(|parent| ids.contains(parent));
+            parent.map(|p| p.as_str()) == parent_id
+        })
+        .map(|(name, _)| name.to_owned())
+        .collect();
+    names.sort_by_key(|name| name.to_lowercase());
+    names
+}
+
+fn push_project(config: &Config, buffer: &mut String, name: &str, depth: usize) {
+    let project = &config.projects[name];
+    buffer.push_str(&format!("\n{}- {}", "  ".repeat(depth - 1), name));
+    if let Some(color) = &project.color {
+        buffer.push_str(&format!(" {}", color_dot(color)));
+    }
+
+    for child in child_names(config, Some(&project.id)) {
+        push_project(config, buffer, &child, depth + 1);
+    }
+}
+
+/// A dot in the closest terminal color to a Todoist project color
+fn color_dot(color: &str) -> ColoredString {
+    let dot = "●";
+    match color {
+        "berry_red" | "red" | "salmon" => dot.red(),
+        "orange" | "yellow" | "olive_green" => dot.yellow(),
+        "lime_green" | "green" | "mint_green" => dot.green(),
+        "teal" | "sky_blue" | "light_blue" => dot.cyan(),
+        "blue" | "grape" => dot.blue(),
+        "violet" | "lavender" | "magenta" => dot.magenta(),
+        "charcoal" | "grey" | "taupe" => dot.bright_black(),
+        _ => dot.normal(),
+    }
+}
+
 /// Add a project to the projects HashMap in Config
-pub fn add(config: Config, params: Vec<String>) -> Result<String, String> {
+pub fn add(config: Config, params: Vec<String>) -> Result<String, Error> {
     let mut params = params;
-    let num = params
-        .pop()
-        .ok_or(ADD_ERROR)?
-        .parse::<u32>()
-        .or(Err(ADD_ERROR))?;
-
-    let name = params.pop().ok_or(ADD_ERROR)?;
+    let id = params.pop().ok_or_else(add_error)?;
+    let name = params.pop().ok_or_else(add_error)?;
 
-    config.add_project(name, num).save()
+    config.add_project(name, config::Project::new(&id)).save()
 }
 
 /// Remove a project from the projects HashMap in Config
-pub fn remove(config: Config, project_name: &str) -> Result<String, String> {
+pub fn remove(config: Config, project_name: &str) -> Result<String, Error> {
     config.remove_project(project_name).save()
 }
 
-pub fn project_id(config: &Config, project_name: &str) -> Result<String, String> {
-    let project_id = config
+/// Fetch all projects from Todoist and store them in config.
+/// The Todoist inbox is stored as "inbox", projects added by hand are kept.
+pub fn sync_projects(config: Config) -> Result<String, Error> {
+    let cache = cache::sync(&config)?;
+    let count = cache.projects.len();
+    let names = project_names(&cache);
+
+    // Projects that were deleted or archived in Todoist are no longer returned
+    let mut config = config;
+    let ids: Vec<&String> = cache.projects.iter().map(|project| &project.id).collect();
+    let pruned: Vec<String> = config
+        .projects
+        .iter()
+        .filter(|(_, stored)| !ids.contains(&&stored.id))
+        .map(|(name, _)| name.clone())
+        .collect();
+    config
         .projects
-        .get(project_name)
-        .ok_or(format!(
+        .retain(|_, stored| ids.contains(&&stored.id));
+
+    let mut renamed: Vec<String> = Vec::new();
+    for project in cache.projects {
+        let name = names[&project.id].clone();
+        if !project.inbox_project && name != project.name {
+            renamed.push(format!("{} is stored as {}", project.name, name));
+        }
+        config.projects.retain(|_, stored| stored.id != project.id);
+        config = config.add_project(
+            name,
+            config::Project {
+                id: project.id,
+                parent_id: project.parent_id,
+                color: project.color,
+            },
+        );
+    }
+    config.clone().save()?;
+
+    let mut buffer = green_string(&format!("Synced {} projects", count));
+    if !pruned.is_empty() {
+        let mut pruned = pruned;
+        pruned.sort();
+        buffer.push_str(&format!(
+            "\nRemoved projects no longer in Todoist: {}",
+            pruned.join(", ")
+        ));
+    }
+    if !renamed.is_empty() {
+        renamed.sort();
+        buffer.push_str(&format!(
+            "\nProjects with the same name: {}",
+            renamed.join(", ")
+        ));
+    }
+    Ok(format!("{}\n{}", buffer, list(config)?))
+}
+
+/// Name to store each project under by id. Projects that share a name are qualified
+/// with their parent, i.e. Home/Garden, and with their id when that is not enough.
+fn project_names(cache: &Cache) -> HashMap<String, String> {
+    let name = |project: &cache::Project| {
+        if project.inbox_project {
+            String::from("inbox")
+        } else {
+            project.name.clone()
+        }
+    };
+    let count = |names: &HashMap<String, String>, name: &String| {
+        names.values().filter(|other| *other == name).count()
+    };
+
+    let mut names: HashMap<String, String> = cache
+        .projects
+        .iter()
+        .map(|project| (project.id.clone(), name(project)))
+        .collect();
+    let plain = names.clone();
+    for project in cache.projects.iter() {
+        if count(&plain, &plain[&project.id]) < 2 {
+            continue;
+        }
+        let parent = project
+            .parent_id
+            .as_ref()
+            .and_then(|parent_id| plain.get(parent_id));
+        if let Some(parent) = parent {
+            names.insert(
+                project.id.clone(),
+                format!("{}/{}", parent, plain[&project.id]),
+            );
+        }
+    }
+    let qualified = names.clone();
+    for project in cache.projects.iter() {
+        if count(&qualified, &qualified[&project.id]) > 1 {
+            names.insert(
+                project.id.clone(),
+                format!("{} ({})", qualified[&project.id], project.id),
+            );
+        }
+    }
+    names
+}
+
+/// Find a project id by name, exact match first, then ignoring case, then by unique prefix
+pub fn project_id(config: &Config, project_name: &str) -> Result<String, Error> {
+    match match_name(config.projects.keys().collect(), project_name) {
+        Ok(name) => Ok(config.projects[name].id.clone()),
+        Err(names) if names.is_empty() => Err(Error::Input(format!(
             "Project {} not found, please add it to config",
             project_name
-        ))?
-        .to_string();
+        ))),
+        Err(names) => Err(ambiguous("Project", project_name, &names)),
+    }
+}
 
-    Ok(project_id)
+/// Find a section id by name within a project, matching names the same way as project_id
+pub fn section_id(cache: &Cache, project_id: &str, section_name: &str) -> Result<String, Error> {
+    let sections = cache.sections_for_project(project_id);
+    match match_name(sections.iter().map(|s| &s.name).collect(), section_name) {
+        Ok(name) => Ok(sections
+            .iter()
+            .find(|section| &section.name == name)
+            .map(|section| section.id.clone())
+            .unwrap_or_default()),
+        Err(names) if names.is_empty() => Err(Error::Input(format!(
+            "Section {} not found in project",
+            section_name
+        ))),
+        Err(names) => Err(ambiguous("Section", section_name, &names)),
+    }
 }
 
-/// Get the next item by priority and save its id to config
-pub fn next_item(config: Config, project_name: &str) -> Result<String, String> {
+/// The name that matches exactly, ignoring case or by unique prefix,
+/// otherwise every candidate, which is empty when nothing matches
+fn match_name<'a>(names: Vec<&'a String>, query: &str) -> Result<&'a String, Vec<&'a String>> {
+    if let Some(name) = names.iter().find(|name| name.as_str() == query) {
+        return Ok(name);
+    }
+
+    let lowercase = query.to_lowercase();
+    let find = |matches: &dyn Fn(&str) -> bool| -> Vec<&'a String> {
+        let mut found: Vec<&String> = names
+            .iter()
+            .copied()
+            .filter(|name| matches(&name.to_lowercase()))
+            .collect();
+        found.sort();
+        found
+    };
+
+    let exact = find(&|name| name == lowercase);
+    let candidates = if exact.is_empty() {
+        find(&|name| !lowercase.is_empty() && name.starts_with(&lowercase))
+    } else {
+        exact
+    };
+
+    match candidates.as_slice() {
+        [name] => Ok(name),
+        _ => Err(candidates),
+    }
+}
+
+fn ambiguous(kind: &str, query: &str, names: &[&String]) -> Error {
+    Error::Input(format!(
+        "{} {} is ambiguous, could be {}",
+        kind,
+        query,
+        names
+            .iter()
+            .map(|name| name.as_str())
+            .collect::<Vec<&str>>()
+            .join(", ")
+    ))
+}
+
+/// Get the next items by priority, queueing every candidate in config so that
+/// complete and skip can step through them
+pub fn next_item(
+    config: Config,
+    project_name: &str,
+    label: Option<&str>,
+    section: Option<&str>,
+    count: usize,
+    explain: bool,
+) -> Result<String, Error> {
+    if explain && config.output != Format::Plain {
+        return Err(Error::Input(String::from(
+            "--explain only works with plain output",
+        )));
+    }
     let project_id = projects::project_id(&config, project_name)?;
-    let items = request::items_for_project(&config, &project_id)?;
+    let cache = cache::sync(&config)?;
+    let section_id = match section {
+        Some(name) => Some(section_id(&cache, &project_id, name)?),
+        None => None,
+    };
+    let items = items::filter_leaves(cache.items_for_project(&project_id));
+    let items = items::filter_by_section(items, section_id.as_deref());
+    let items = items::filter_by_label(items, label);
     let filtered_items = items::filter_not_in_future(items, &config)?;
-    let maybe_item = items::sort_by_value(filtered_items, &config)
-        .first()
-        .map(|item| item.to_owned());
+    let candidates = items::sort_by_value(filtered_items, &config);
+    let top = &candidates[..count.min(candidates.len())];
 
-    match maybe_item {
-        Some(item) => {
-            config.set_next_id(item.id.clone()).save()?;
-            Ok(item.fmt(&config))
-        }
-        None => Ok(green_string("No items on list")),
+    let config = config.set_focus(candidates.iter().map(|item| item.id.clone()).collect());
+    if top.len() > 1 {
+        save_listing(&config, top)?;
+    } else {
+        config.clone().save()?;
+    }
+
+    if config.output != Format::Plain {
+        return output::items(&config, top);
+    }
+    let text = match top {
+        [] => return Ok(green_string("No items on list")),
+        [item] => item.fmt(&config),
+        items => format!(
+            "{}{}",
+            green_string(&format!("Next {} tasks", items.len())),
+            numbered(&config, items)
+        ),
+    };
+    match explain {
+        true => Ok(format!(
+            "{}\n\n{}",
+            text,
+            explain_scores(&config, &candidates)
+        )),
+        false => Ok(text),
+    }
+}
+
+/// Put the next task at the back of the focus queue and show the one after it
+pub fn skip(config: Config) -> Result<String, Error> {
+    let id = config
+        .next_id
+        .clone()
+        .ok_or_else(|| Error::Input(String::from("No task to skip, get one with next first")))?;
+
+    let mut focus = config.remove_from_focus(std::slice::from_ref(&id)).focus;
+    if focus.is_empty() {
+        return Ok(green_string("No other tasks in focus"));
+    }
+    focus.push(id);
+    let config = config.set_focus(focus);
+    config.clone().save()?;
+    focused_item(&config)
+}
+
+/// The next task from the cache, without syncing
+fn focused_item(config: &Config) -> Result<String, Error> {
+    let cache = Cache::load(config)?;
+    match &config.next_id {
+        None => Ok(String::new()),
+        Some(id) => match cache.items.iter().find(|item| &item.id == id) {
+            Some(item) => Ok(item.fmt(config)),
+            None => Ok(format!("\n{}", id)),
+        },
+    }
+}
+
+/// The score of each candidate and how it was reached, highest first
+fn explain_scores(config: &Config, candidates: &[Item]) -> String {
+    let mut buffer = green_string("Scores");
+    for item in candidates {
+        let score = item.score(config);
+        buffer.push_str(&format!(
+            "\n{:>4}  {} {}",
+            score.total(),
+            item.content,
+            format!("({})", score.explain()).bright_black()
+        ));
     }
+    buffer
 }
 
 // Scheduled that are today and have a time on them (AKA appointments)
-pub fn scheduled_items(config: &Config, project_name: &str) -> Result<String, String> {
+pub fn scheduled_items(config: &Config, project_name: &str) -> Result<String, Error> {
     let project_id = projects::project_id(config, project_name)?;
 
-    let items = request::items_for_project(config, &project_id)?;
+    let items = cache::items_for_project(config, &project_id)?;
     let filtered_items = items::filter_today_and_has_time(items, config);
 
+    if config.output != Format::Plain {
+        let items = items::sort_by_datetime(filtered_items, config);
+        save_listing(config, &items)?;
+        return output::items(config, &items);
+    }
     if filtered_items.is_empty() {
         return Ok(String::from("No scheduled items found"));
     }
@@ -86,51 +391,197 @@ pub fn scheduled_items(config: &Config, project_name: &str) -> Result<String, St
     let mut buffer = String::new();
     buffer.push_str(&green_string(&format!("Schedule for {}", project_name)));
 
-    for item in items::sort_by_datetime(filtered_items, config) {
-        buffer.push('\n');
-        buffer.push_str(&item.fmt(config));
+    let items = items::sort_by_datetime(filtered_items, config);
+    buffer.push_str(&numbered(config, &items));
+    for (first, second) in items::overlaps(&items, config) {
+        let warning = format!("⚠ {} overlaps {}", first.content, second.content);
+        buffer.push_str(&format!("\n{}", warning.yellow()));
     }
+    save_listing(config, &items)?;
     Ok(buffer)
 }
 
-/// All items for a project
-pub fn all_items(config: &Config, project_name: &str) -> Result<String, String> {
+/// All items for a project grouped by section, with subtasks indented under their parent
+/// or prefixed with the names of their parents when namespace is true
+pub fn all_items(
+    config: &Config,
+    project_name: &str,
+    label: Option<&str>,
+    section: Option<&str>,
+    namespace: bool,
+) -> Result<String, Error> {
     let project_id = projects::project_id(config, project_name)?;
+    let cache = cache::sync(config)?;
+    let section_id = match section {
+        Some(name) => Some(section_id(&cache, &project_id, name)?),
+        None => None,
+    };
+
+    let items =
+        items::filter_by_section(cache.items_for_project(&project_id), section_id.as_deref());
+    let items = items::sort_by_datetime(items::filter_by_label(items, label), config);
+
+    // Tasks without a known section come first, then each section in order
+    let sections = cache.sections_for_project(&project_id);
+    let (unsectioned, mut sectioned): (Vec<Item>, Vec<Item>) =
+        items.into_iter().partition(|item| {
+            !sections
+                .iter()
+                .any(|section| item.section_id.as_ref() == Some(&section.id))
+        });
+    let mut groups = vec![(None, items::tree(unsectioned))];
+    for section in sections.iter() {
+        let (in_section, rest) = sectioned
+            .into_iter()
+            .partition(|item| item.section_id.as_ref() == Some(&section.id));
+        sectioned = rest;
+        groups.push((Some(section.name.as_str()), items::tree(in_section)));
+    }
 
-    let items = request::items_for_project(config, &project_id)?;
+    let items: Vec<Item> = groups
+        .iter()
+        .flat_map(|(_, tree)| tree.iter().map(|(_, item)| item.clone()))
+        .collect();
+    save_listing(config, &items)?;
+    if config.output != Format::Plain {
+        return output::items(config, &items);
+    }
 
     let mut buffer = String::new();
     buffer.push_str(&green_string(&format!("Tasks for {}", project_name)));
 
-    for item in items::sort_by_datetime(items, config) {
-        buffer.push('\n');
-        buffer.push_str(&item.fmt(config));
+    let mut count = 0;
+    for (name, tree) in groups.iter().filter(|(_, tree)| !tree.is_empty()) {
+        if let Some(name) = name {
+            buffer.push_str(&format!("\n\n{}", name.bold()));
+        }
+        buffer.push_str(&numbered_tree(config, tree, namespace, count));
+        count += tree.len();
+    }
+    Ok(buffer)
+}
+
+/// Tasks due in the next days across all projects in config, overdue tasks first,
+/// then grouped by date and labelled with their project
+pub fn agenda(config: &Config, days: i64) -> Result<String, Error> {
+    let cache = cache::sync(config)?;
+    let names: HashMap<&String, &String> = config
+        .projects
+        .iter()
+        .map(|(name, project)| (&project.id, name))
+        .collect();
+    let today = time::today_date(config);
+    let items: Vec<Item> = cache
+        .items
+        .into_iter()
+        .filter(|item| names.contains_key(&item.project_id))
+        .collect();
+
+    let (overdue, items): (Vec<Item>, Vec<Item>) = items
+        .into_iter()
+        .filter(|item| item.due_date(config).is_some())
+        .partition(|item| item.is_overdue(config));
+    let mut groups = vec![(
+        "Overdue".red().to_string(),
+        items::sort_by_value(overdue, config),
+    )];
+    for offset in 0..days {
+        let date = today + Duration::days(offset);
+        let due: Vec<Item> = items
+            .iter()
+            .filter(|item| item.due_date(config) == Some(date))
+            .cloned()
+            .collect();
+        let due = items::sort_by_datetime(items::sort_by_value(due, config), config);
+        groups.push((time::format_date(&date, config).bold().to_string(), due));
+    }
+
+    let items: Vec<Item> = groups
+        .iter()
+        .flat_map(|(_, items)| items.iter().cloned())
+        .collect();
+    save_listing(config, &items)?;
+    if config.output != Format::Plain {
+        return output::items(config, &items);
+    }
+    if items.is_empty() {
+        return Ok(green_string("Nothing due"));
+    }
+
+    let mut buffer = String::new();
+    let mut count = 0;
+    for (header, items) in groups.iter().filter(|(_, items)| !items.is_empty()) {
+        buffer.push_str(&format!("\n\n{}", header));
+        for item in items {
+            count += 1;
+            let text = item.fmt(config).trim_start().to_owned();
+            let (first, rest) = text.split_once('\n').unwrap_or((&text, ""));
+            let project = format!("#{}", names[&item.project_id]).bright_black();
+            buffer.push_str(&format!(
+                "\n{} {} {}{}",
+                format!("{}.", count).bright_black(),
+                first,
+                project,
+                rest.lines()
+                    .map(|line| format!("\n   {}", line))
+                    .collect::<String>()
+            ));
+        }
     }
+    Ok(buffer.trim_start().to_owned())
+}
+
+/// Tasks in any project that match a Todoist filter query, sorted by value
+pub fn filter_items(config: &Config, query: &str) -> Result<String, Error> {
+    let filter = Filter::parse(query, config)?;
+    let cache = cache::sync(config)?;
+    let items: Vec<Item> = cache
+        .items
+        .iter()
+        .filter(|item| filter.matches(item, config, &cache))
+        .cloned()
+        .collect();
+    let items = items::sort_by_value(items, config);
+
+    save_listing(config, &items)?;
+    if config.output != Format::Plain {
+        return output::items(config, &items);
+    }
+    if items.is_empty() {
+        return Ok(format!("No tasks match {}", query));
+    }
+
+    let mut buffer = green_string(&format!("Tasks matching {}", query));
+    buffer.push_str(&numbered(config, &items));
     Ok(buffer)
 }
 
-/// Empty the inbox by sending items to other projects one at a time
-pub fn sort_inbox(config: Config) -> Result<String, String> {
+/// Empty the inbox by choosing a project for each item, then sending all moves at once
+pub fn sort_inbox(config: Config) -> Result<String, Error> {
     let inbox_id = projects::project_id(&config, "inbox")?;
 
-    let items = request::items_for_project(&config, &inbox_id)?;
+    let cache = cache::sync(&config)?;
+    let items = cache.items_for_project(&inbox_id);
 
     if items.is_empty() {
         Ok(green_string("No tasks to sort in inbox"))
     } else {
-        projects::list(config.clone())?;
-        for item in items.iter() {
-            move_item_to_project(config.clone(), item.to_owned())?;
+        println!("{}", projects::list(config.clone())?);
+        let mut decisions: Vec<(String, Command)> = Vec::new();
+        for item in items.into_iter() {
+            if let Some(command) = move_item_to_project(&config, &cache, &item)? {
+                decisions.push((item.content, command));
+            }
         }
-        Ok(green_string("Successfully sorted inbox"))
+        submit_and_summarize(&config, decisions, "Successfully sorted inbox")
     }
 }
 
-/// Prioritize all unprioritized items in a project
-pub fn prioritize_items(config: &Config, project_name: &str) -> Result<String, String> {
+/// Prioritize all unprioritized items in a project, then send all updates at once
+pub fn prioritize_items(config: &Config, project_name: &str) -> Result<String, Error> {
     let inbox_id = projects::project_id(config, project_name)?;
 
-    let items = request::items_for_project(config, &inbox_id)?;
+    let items = cache::items_for_project(config, &inbox_id)?;
 
     let unprioritized_items: Vec<Item> = items
         .into_iter()
@@ -142,45 +593,1478 @@ pub fn prioritize_items(config: &Config, project_name: &str) -> Result<String, S
             .green()
             .to_string())
     } else {
-        for item in unprioritized_items.iter() {
-            items::set_priority(config.clone(), item.to_owned());
+        let mut decisions: Vec<(String, Command)> = Vec::new();
+        for item in unprioritized_items.into_iter() {
+            if let Some(command) = items::set_priority(config, &item)? {
+                decisions.push((item.content, command));
+            }
         }
-        Ok(format!("Successfully prioritized {}", project_name)
-            .green()
-            .to_string())
+        submit_and_summarize(
+            config,
+            decisions,
+            &format!("Successfully prioritized {}", project_name),
+        )
     }
 }
 
-pub fn move_item_to_project(config: Config, item: Item) -> Result<String, String> {
-    println!("{}", item.fmt(&config));
+/// Ask where an item should go, returning None when it is skipped
+pub fn move_item_to_project(
+    config: &Config,
+    cache: &Cache,
+    item: &Item,
+) -> Result<Option<Command>, Error> {
+    println!("{}", item.fmt(config));
+
+    loop {
+        let project_name = config::get_input(
+            "Enter destination project name, (c)omplete or leave empty to skip:",
+        )?;
+
+        match project_name.as_str() {
+            "" => return Ok(None),
+            "complete" | "c" => return Ok(Some(Command::item_close(&item.id))),
+            _ => match projects::project_id(config, &project_name) {
+                Ok(project_id) => return move_item_to_section(cache, item, &project_id),
+                Err(err) => println!("{}", err.to_string().red()),
+            },
+        }
+    }
+}
 
-    let project_name = config::get_input("Enter destination project name or (c)omplete:")?;
+/// Ask for a section when the project has any, then move the item into it
+fn move_item_to_section(
+    cache: &Cache,
+    item: &Item,
+    project_id: &str,
+) -> Result<Option<Command>, Error> {
+    let sections = cache.sections_for_project(project_id);
+    if sections.is_empty() {
+        return Ok(Some(Command::item_move(&item.id, project_id)));
+    }
 
-    match project_name.as_str() {
-        "complete" | "c" => {
-            request::complete_item(config.set_next_id(item.id))?;
-            Ok(green_string("✓"))
+    let names: Vec<&str> = sections.iter().map(|s| s.name.as_str()).collect();
+    println!("Sections: {}", names.join(", "));
+    loop {
+        let section_name = config::get_input("Enter section name or leave empty for none:")?;
+        if section_name.is_empty() {
+            return Ok(Some(Command::item_move(&item.id, project_id)));
         }
-        _ => {
-            request::move_item(config, item, &project_name)?;
-            Ok(green_string("✓"))
+        match section_id(cache, project_id, &section_name) {
+            Ok(section_id) => {
+                return Ok(Some(Command::item_move_to_section(&item.id, &section_id)))
+            }
+            Err(err) => println!("{}", err.to_string().red()),
         }
     }
 }
 
+/// Send commands as one batch and report the result of each, labelled by task content
+fn submit_decisions(config: &Config, decisions: Vec<(String, Command)>) -> Result<String, Error> {
+    if decisions.is_empty() {
+        return Ok(String::from("No changes made"));
+    }
+
+    let response = send_decisions(config, &decisions)?;
+    Ok(report_decisions(&decisions, &response))
+}
+
+/// Like submit_decisions, ending with the summary only when no change failed
+fn submit_and_summarize(
+    config: &Config,
+    decisions: Vec<(String, Command)>,
+    summary: &str,
+) -> Result<String, Error> {
+    if decisions.is_empty() {
+        return Ok(format!("No changes made\n{}", green_string(summary)));
+    }
+
+    let response = send_decisions(config, &decisions)?;
+    let failed = decisions
+        .iter()
+        .filter(|(_, command)| matches!(response.status(&command.uuid), Status::Failed(_)))
+        .count();
+    let summary = match failed {
+        0 => green_string(summary),
+        failed => format!("{} of {} changes failed", failed, decisions.len())
+            .red()
+            .to_string(),
+    };
+    Ok(format!(
+        "{}\n{}",
+        report_decisions(&decisions, &response),
+        summary
+    ))
+}
+
+fn send_decisions(config: &Config, decisions: &[(String, Command)]) -> Result<SyncResponse, Error> {
+    let commands: Vec<Command> = decisions
+        .iter()
+        .map(|(_, command)| command.clone())
+        .collect();
+    queue::submit_all(config, commands)
+}
+
+/// The status of each command, labelled by task content
+fn report_decisions(decisions: &[(String, Command)], response: &SyncResponse) -> String {
+    let mut buffer = String::new();
+    for (label, command) in decisions {
+        let line = match response.status(&command.uuid) {
+            Status::Ok => format!("✓ {}", label).green(),
+            Status::Queued => format!("… {} (queued)", label).yellow(),
+            Status::Failed(message) => format!("✗ {}: {}", label, message).red(),
+        };
+        buffer.push_str(&format!("\n{}", line));
+    }
+    buffer
+}
+
+/// Items formatted with their number in the listing, for use with complete
+fn numbered(config: &Config, items: &[Item]) -> String {
+    let tree: Vec<(usize, Item)> = items.iter().map(|item| (0, item.clone())).collect();
+    numbered_tree(config, &tree, false, 0)
+}
+
+/// Like numbered, with subtasks indented by depth or prefixed with their parents.
+/// Numbers start after offset, for listings printed in several parts.
+fn numbered_tree(
+    config: &Config,
+    tree: &[(usize, Item)],
+    namespace: bool,
+    offset: usize,
+) -> String {
+    let mut buffer = String::new();
+    let mut parents: Vec<&str> = Vec::new();
+    for (index, (depth, item)) in tree.iter().enumerate() {
+        parents.truncate(*depth);
+        let text = item.fmt(config).trim_start().to_owned();
+        let (indent, text) = if namespace {
+            let names: String = parents.iter().map(|name| format!("{} > ", name)).collect();
+            (String::new(), format!("{}{}", names, text))
+        } else {
+            let indent = "   ".repeat(*depth);
+            let text = text.replace('\n', &format!("\n{}", indent));
+            (indent, text)
+        };
+        buffer.push_str(&format!(
+            "\n\n{}{} {}",
+            indent,
+            format!("{}.", offset + index + 1).bright_black(),
+            text
+        ));
+        parents.push(&item.content);
+    }
+    buffer
+}
+
+/// Remember the order of a listing so tasks can be referred to by number
+fn save_listing(config: &Config, items: &[Item]) -> Result<String, Error> {
+    let ids = items.iter().map(|item| item.id.clone()).collect();
+    config.set_listing(ids).save()
+}
+
+/// Turn numbers from the last listing into task ids, anything else is taken to be an id
+pub fn resolve_ids(config: &Config, targets: &[String]) -> Result<Vec<String>, Error> {
+    targets
+        .iter()
+        .map(|target| match target.parse::<usize>() {
+            Ok(index) if index >= 1 && index <= config.listing.len() => {
+                Ok(config.listing[index - 1].clone())
+            }
+            // Too short to be a task id, most likely a mistyped number
+            Ok(index) if target.len() < MIN_ID_LENGTH => Err(Error::Input(format!(
+                "No task {} in the last listing, which has {} tasks",
+                index,
+                config.listing.len()
+            ))),
+            _ => Ok(target.to_owned()),
+        })
+        .collect()
+}
+
+/// Label a task id with its content when it is in the cache
+fn label(cache: &Cache, id: &str) -> String {
+    cache
+        .items
+        .iter()
+        .find(|item| item.id == id)
+        .map(|item| item.content.clone())
+        .unwrap_or_else(|| String::from(id))
+}
+
+/// Complete tasks by id or number in the last listing, or the last next task when none are given
+pub fn complete_items(config: Config, targets: &[String]) -> Result<String, Error> {
+    let cache = Cache::load(&config)?;
+    let ids = match targets {
+        [] => vec![config.next_id.clone().ok_or_else(|| {
+            Error::Input(String::from("No task to complete, get one with next first"))
+        })?],
+        targets => resolve_ids(&config, targets)?,
+    };
+    let decisions: Vec<(String, Command)> = ids
+        .iter()
+        .map(|id| (label(&cache, id), Command::item_close(id)))
+        .collect();
+    let commands: Vec<Command> = decisions
+        .iter()
+        .map(|(_, command)| command.clone())
+        .collect();
+    let response = queue::submit_all(&config, commands)?;
+    let statuses: Vec<Status> = decisions
+        .iter()
+        .map(|(_, command)| response.status(&command.uuid))
+        .collect();
+    let mut report = report_decisions(&decisions, &response)
+        .trim_start()
+        .to_owned();
+
+    let done: Vec<String> = ids
+        .iter()
+        .zip(statuses.iter())
+        .filter(|(_, status)| !matches!(status, Status::Failed(_)))
+        .map(|(id, _)| id.clone())
+        .collect();
+    let focused = |id: &String| config.focus.contains(id) || config.next_id.as_ref() == Some(id);
+    let config = match done.iter().any(focused) {
+        true => {
+            let config = config.remove_from_focus(&done);
+            config.clone().save()?;
+            config
+        }
+        false => config,
+    };
+
+    let closed: Vec<String> = ids
+        .iter()
+        .zip(statuses.iter())
+        .filter(|(_, status)| **status == Status::Ok)
+        .map(|(id, _)| id.clone())
+        .collect();
+    report.push_str(&next_occurrences(&config, &cache, &closed));
+    // Progress is only fetched when something was closed, not when the close was queued offline
+    if config.goal_progress.unwrap_or(true) && statuses.contains(&Status::Ok) {
+        report = with_goal_progress(&config, report);
+    }
+    if targets.is_empty() {
+        let next = focused_item(&config)?;
+        if !next.is_empty() {
+            report.push_str(&format!("\n{}{}", green_string("Next:"), next));
+        }
+    }
+    Ok(report)
+}
+
+/// The new due dates the server gave to closed recurring tasks, one per line
+fn next_occurrences(config: &Config, cache: &Cache, closed: &[String]) -> String {
+    let recurring: Vec<&String> = closed
+        .iter()
+        .filter(|id| {
+            cache.items.iter().any(|item| {
+                item.id == **id && item.due.as_ref().is_some_and(|due| due.is_recurring)
+            })
+        })
+        .collect();
+    if recurring.is_empty() {
+        return String::new();
+    }
+
+    let synced = match cache::sync(config) {
+        Ok(synced) => synced,
+        Err(_) => return String::new(),
+    };
+    synced
+        .items
+        .iter()
+        .filter(|item| recurring.contains(&&item.id))
+        .filter_map(|item| match item.fmt_due(config) {
+            Ok(Some(due)) => Some(format!("\n{} next due {}", item.content, due)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Add progress towards today's goal to a report, when it can be fetched
+fn with_goal_progress(config: &Config, report: String) -> String {
+    match karma::daily_goal_progress(config) {
+        Some(progress) => format!("{}\n{}", report, progress),
+        None => report,
+    }
+}
+
+/// Reopen completed tasks by id or number in the last listing
+pub fn uncomplete_items(config: Config, targets: &[String]) -> Result<String, Error> {
+    let ids = resolve_ids(&config, targets)?;
+    let cache = Cache::load(&config)?;
+    let decisions = ids
+        .iter()
+        .map(|id| (label(&cache, id), Command::item_uncomplete(id)))
+        .collect();
+    let report = submit_decisions(&config, decisions)?;
+
+    Ok(report.trim_start().to_owned())
+}
+
+/// Change several fields of a task in one request, then show the updated task
+pub fn modify_item(config: Config, target: &str, changes: &Changes) -> Result<String, Error> {
+    if changes.is_empty() {
+        return Err(Error::Input(String::from(
+            "Nothing to modify, for more information try modify --help",
+        )));
+    }
+
+    let id = resolve_ids(&config, &[String::from(target)])?.remove(0);
+    let command = Command::item_update(&id, changes);
+    let uuid = command.uuid.clone();
+
+    match queue::submit_all(&config, vec![command])?.status(&uuid) {
+        Status::Ok => Ok(request::get_item(&config, &id)?.fmt(&config)),
+        Status::Queued => Ok(String::from(
+            "Queued, the task will be updated when back online",
+        )),
+        Status::Failed(message) => Err(Error::Command(message)),
+    }
+}
+
+/// List personal labels and labels in use on tasks
+pub fn labels(config: &Config) -> Result<String, Error> {
+    let names = cache::sync(config)?.label_names();
+    if names.is_empty() {
+        return Ok(String::from("No labels found"));
+    }
+
+    let mut buffer = green_string("Labels");
+    for name in names {
+        buffer.push_str(&format!("\n - @{}", name));
+    }
+    Ok(buffer)
+}
+
+/// Add labels to tasks, or remove them when remove is true, keeping their other labels
+pub fn update_labels(
+    config: &Config,
+    targets: &[String],
+    labels: &[String],
+    remove: bool,
+) -> Result<String, Error> {
+    let cache = Cache::load(config)?;
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|label| String::from(label.trim_start_matches('@')))
+        .collect();
+
+    // Todoist label names are case-insensitive
+    let contains = |labels: &[String], label: &String| {
+        labels
+            .iter()
+            .any(|other| other.to_lowercase() == label.to_lowercase())
+    };
+
+    let mut decisions: Vec<(String, Command)> = Vec::new();
+    for id in resolve_ids(config, targets)? {
+        let item = cache.find_item(config, &id)?;
+        let mut new_labels: Vec<String> = item
+            .labels
+            .iter()
+            .filter(|label| !remove || !contains(&labels, label))
+            .cloned()
+            .collect();
+        if !remove {
+            for label in labels.iter() {
+                if !contains(&new_labels, label) {
+                    new_labels.push(label.clone());
+                }
+            }
+        }
+        let changes = Changes {
+            labels: Some(new_labels),
+            ..Changes::default()
+        };
+        decisions.push((item.content, Command::item_update(&item.id, &changes)));
+    }
+
+    Ok(submit_decisions(config, decisions)?.trim_start().to_owned())
+}
+
+/// Move a task to a date in natural language. Recurring tasks keep recurring, which needs
+/// a date that can be resolved here, i.e. tomorrow, friday or 2024-03-01
+pub fn reschedule(config: &Config, target: &str, date: &str) -> Result<String, Error> {
+    let id = resolve_ids(config, &[String::from(target)])?.remove(0);
+    let item = Cache::load(config)?.find_item(config, &id)?;
+    let decision = date_decision(config, &item, date)?;
+    Ok(submit_decisions(config, vec![decision])?
+        .trim_start()
+        .to_owned())
+}
+
+/// Move an item to a day, falling back to Todoist's natural language for dates that
+/// can not be resolved here unless the item is recurring
+fn date_decision(config: &Config, item: &Item, date: &str) -> Result<(String, Command), Error> {
+    let recurring = item.due.as_ref().is_some_and(|due| due.is_recurring);
+
+    match time::parse_date(date, config) {
+        Some(date) => Ok(move_to_date(config, item, date)),
+        None if recurring => Err(Error::Input(format!(
+            "Could not resolve {} to a day, use i.e. tomorrow, friday or 2024-03-01 to keep {} recurring",
+            date, item.content
+        ))),
+        None => Ok((
+            format!("{} → {}", item.content, date),
+            Command::item_update_due(&item.id, date),
+        )),
+    }
+}
+
+/// Push a task back by days from its due date, or from today when it is overdue or has no date
+pub fn postpone(config: &Config, target: &str, days: i64) -> Result<String, Error> {
+    let id = resolve_ids(config, &[String::from(target)])?.remove(0);
+    let item = Cache::load(config)?.find_item(config, &id)?;
+    let today = time::today_date(config);
+    let from = item
+        .due_date(config)
+        .filter(|date| *date >= today)
+        .unwrap_or(today);
+
+    let decision = move_to_date(config, &item, from + Duration::days(days));
+    Ok(submit_decisions(config, vec![decision])?
+        .trim_start()
+        .to_owned())
+}
+
+/// Walk through overdue tasks in all projects, choosing what to do with each,
+/// then send all changes at once
+pub fn triage_overdue(config: &Config) -> Result<String, Error> {
+    let cache = cache::sync(config)?;
+    let overdue: Vec<Item> = cache
+        .items
+        .iter()
+        .filter(|item| config.project_name(&item.project_id).is_some())
+        .filter(|item| item.is_overdue(config))
+        .cloned()
+        .collect();
+
+    if overdue.is_empty() {
+        return Ok(green_string("No overdue tasks"));
+    }
+
+    let mut decisions: Vec<(String, Command)> = Vec::new();
+    for item in items::sort_by_value(overdue, config) {
+        if let Some(decision) = triage_item(config, &item)? {
+            decisions.push(decision);
+        }
+    }
+    submit_and_summarize(config, decisions, "Successfully triaged overdue tasks")
+}
+
+/// Ask what to do with an overdue item, returning None when it is skipped
+fn triage_item(config: &Config, item: &Item) -> Result<Option<(String, Command)>, Error> {
+    println!("{}", item.fmt(config));
+
+    loop {
+        let choice = config::get_input(
+            "(t)oday, to(m)orrow, next (w)eek, another day, (r)emove date, (c)omplete or leave empty to skip:",
+        )?;
+
+        let date = match choice.as_str() {
+            "" => return Ok(None),
+            "complete" | "c" => {
+                return Ok(Some((item.content.clone(), Command::item_close(&item.id))))
+            }
+            "remove date" | "r" => {
+                return Ok(Some((
+                    format!("{} → no date", item.content),
+                    Command::item_update_due(&item.id, "no date"),
+                )))
+            }
+            "t" => "today",
+            "m" => "tomorrow",
+            "w" => "next week",
+            date => date,
+        };
+        match date_decision(config, item, date) {
+            Ok(decision) => return Ok(Some(decision)),
+            Err(Error::Input(message)) => println!("{}", message.red()),
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Move an item to another day, keeping its time of day and recurrence
+fn move_to_date(config: &Config, item: &Item, date: NaiveDate) -> (String, Command) {
+    let due = match (&item.due, item.due_date(config)) {
+        (Some(due), Some(current)) => {
+            // The stored date can be in UTC, so it is shifted rather than replaced
+            let stored = NaiveDate::parse_from_str(&due.date[..10], "%Y-%m-%d").unwrap_or(current);
+            DateInfo {
+                date: format!(
+                    "{}{}",
+                    (stored + (date - current)).format("%Y-%m-%d"),
+                    &due.date[10..]
+                ),
+                ..due.clone()
+            }
+        }
+        (due, _) => DateInfo {
+            date: date.format("%Y-%m-%d").to_string(),
+            timezone: None,
+            ..due.clone().unwrap_or_default()
+        },
+    };
+
+    (
+        format!("{} → {}", item.content, time::format_date(&date, config)),
+        Command::item_update_due_date(&item.id, &due),
+    )
+}
+
 /// Add item to project with natural language processing
-pub fn add_item_to_project(config: Config, task: &str, project: &str) -> Result<String, String> {
-    let item = request::add_item_to_inbox(&config, task)?;
+pub fn add_item_to_project(
+    config: Config,
+    task: &str,
+    project: &str,
+    changes: &Changes,
+) -> Result<String, Error> {
+    let (item, status) = request::add_item_to_inbox(&config, task)?;
+    if !changes.is_empty() {
+        queue::submit(&config, Command::item_update(&item.id, changes))?;
+    }
 
     match project {
-        "inbox" | "i" => Ok(green_string("✓")),
+        "inbox" | "i" => (),
         project => {
             request::move_item(config, item, project)?;
-            Ok(green_string("✓"))
         }
     }
+
+    match status {
+        Status::Queued => Ok(String::from(
+            "… Queued while offline, natural language (dates, #project, @label, p1) is not parsed",
+        )
+        .yellow()
+        .to_string()),
+        _ => Ok(green_string("✓")),
+    }
+}
+
+fn add_error() -> Error {
+    Error::Input(String::from(ADD_ERROR))
 }
 
 fn green_string(str: &str) -> String {
     String::from(str).green().to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::tests::all_ok;
+    use crate::config::tests::mock_config;
+    use crate::scoring::Scoring;
+    use mockito::Matcher;
+    use serde_json::json;
+
+    fn item_json(id: &str, content: &str, priority: u8) -> serde_json::Value {
+        json!({
+            "id": id,
+            "project_id": "2",
+            "content": content,
+            "priority": priority,
+            "checked": false,
+            "description": "",
+            "due": null,
+            "is_deleted": false,
+        })
+    }
+
+    #[test]
+    fn next_item_picks_highest_priority_and_saves_id() {
+        let mut server = mockito::Server::new();
+        let mock = server
+            .mock("POST", "/sync/v9/sync")
+            .with_body(
+                json!({
+                    "sync_token": "abc",
+                    "full_sync": true,
+                    "items": [item_json("10", "Low", 1), item_json("11", "Urgent", 4)],
+                })
+                .to_string(),
+            )
+            .create();
+        let (config, _dir) = mock_config(&server.url());
+
+        let output = next_item(config.clone(), "work", None, None, 1, false).unwrap();
+
+        mock.assert();
+        assert!(output.contains("Urgent"));
+        let saved = Config::load(&config.path).unwrap();
+        assert_eq!(saved.next_id, Some(String::from("11")));
+    }
+
+    #[test]
+    fn next_item_uses_scoring_profile_and_explains() {
+        let mut server = mockito::Server::new();
+        server
+            .mock("POST", "/sync/v9/sync")
+            .with_body(
+                json!({
+                    "sync_token": "abc",
+                    "full_sync": true,
+                    "items": [item_json("10", "Low", 1), item_json("11", "Urgent", 4)],
+                })
+                .to_string(),
+            )
+            .create();
+        let errands = Scoring {
+            p4: 10,
+            ..Scoring::default()
+        };
+        let (config, _dir) = mock_config(&server.url());
+        let config = Config {
+            scoring_profiles: HashMap::from([(String::from("errands"), errands)]),
+            ..config
+        }
+        .with_scoring_profile(Some("errands"))
+        .unwrap();
+
+        let output = next_item(config, "work", None, None, 1, true).unwrap();
+
+        assert!(output.starts_with("\nLow\n\nScores\n  90  Low (no date 80 + p4 10)"));
+        assert!(output.contains("  84  Urgent (no date 80 + p1 4)"));
+    }
+
+    #[test]
+    fn focus_queue_steps_through_candidates() {
+        let mut server = mockito::Server::new();
+        server
+            .mock("POST", "/sync/v9/sync")
+            .match_body(Matcher::PartialJson(json!({"sync_token": "*"})))
+            .with_body(
+                json!({
+                    "sync_token": "abc",
+                    "full_sync": true,
+                    "items": [item_json("10", "Low", 1), item_json("11", "Urgent", 4), item_json("12", "Soon", 3)],
+                })
+                .to_string(),
+            )
+            .expect(1)
+            .create();
+        let close = server
+            .mock("POST", "/sync/v9/sync")
+            .match_body(Matcher::PartialJson(json!({
+                "commands": [{"type": "item_close", "args": {"id": "12"}}]
+            })))
+            .with_body_from_request(all_ok)
+            .create();
+        let (config, _dir) = mock_config(&server.url());
+
+        let output = next_item(config.clone(), "work", None, None, 2, false).unwrap();
+        assert!(output.contains("1. Urgent"));
+        assert!(output.contains("2. Soon"));
+        assert!(!output.contains("Low"));
+
+        let config = Config::load(&config.path).unwrap();
+        assert_eq!(config.focus, vec!["11", "12", "10"]);
+        assert_eq!(skip(config.clone()).unwrap(), "\nSoon");
+
+        let config = Config::load(&config.path).unwrap();
+        assert_eq!(config.focus, vec!["12", "10", "11"]);
+        let output = complete_items(config.clone(), &[]).unwrap();
+        close.assert();
+        assert!(output.ends_with("Next:\nLow"));
+        assert_eq!(
+            Config::load(&config.path).unwrap().next_id,
+            Some(String::from("10"))
+        );
+    }
+
+    #[test]
+    fn next_item_as_json() {
+        let mut server = mockito::Server::new();
+        server
+            .mock("POST", "/sync/v9/sync")
+            .with_body(
+                json!({"sync_token": "abc", "full_sync": true, "items": [item_json("11", "Urgent", 4)]})
+                    .to_string(),
+            )
+            .create();
+        let (config, _dir) = mock_config(&server.url());
+        let config = Config {
+            output: Format::Json,
+            ..config
+        };
+
+        let output: serde_json::Value =
+            serde_json::from_str(&next_item(config, "work", None, None, 1, false).unwrap())
+                .unwrap();
+
+        assert_eq!(output[0]["id"], "11");
+        assert_eq!(output[0]["priority"], 4);
+        assert_eq!(output[0]["is_overdue"], false);
+        assert_eq!(output[0]["sort_value"], 84);
+    }
+
+    #[test]
+    fn next_item_with_unknown_project() {
+        let (config, _dir) = mock_config("http://localhost");
+
+        assert_eq!(
+            next_item(config, "missing", None, None, 1, false),
+            Err(Error::Input(String::from(
+                "Project missing not found, please add it to config"
+            )))
+        );
+    }
+
+    #[test]
+    fn project_id_ignores_case_and_accepts_unique_prefix() {
+        let (config, _dir) = mock_config("http://localhost");
+        let config = config
+            .add_project(String::from("Writing"), config::Project::new("3"))
+            .add_project(String::from("Workout"), config::Project::new("4"));
+
+        assert_eq!(project_id(&config, "WORK"), Ok(String::from("2")));
+        assert_eq!(project_id(&config, "wri"), Ok(String::from("3")));
+        assert_eq!(project_id(&config, "Inb"), Ok(String::from("1")));
+        assert_eq!(
+            project_id(&config, "w"),
+            Err(Error::Input(String::from(
+                "Project w is ambiguous, could be Workout, Writing, work"
+            )))
+        );
+    }
+
+    #[test]
+    fn sync_projects_stores_ids_parents_and_colors() {
+        let mut server = mockito::Server::new();
+        server
+            .mock("POST", "/sync/v9/sync")
+            .with_body(
+                json!({
+                    "sync_token": "abc",
+                    "full_sync": true,
+                    "projects": [
+                        {"id": "6Jf8VQXxpwv56VQ7", "name": "Inbox", "inbox_project": true},
+                        {"id": "6X7rM8997g3RQmvh", "name": "Home", "color": "blue"},
+                        {"id": "6X7rfFVPjhvv84XG", "name": "Garden", "parent_id": "6X7rM8997g3RQmvh"},
+                    ],
+                })
+                .to_string(),
+            )
+            .create();
+        let (config, _dir) = mock_config(&server.url());
+
+        sync_projects(config.clone()).unwrap();
+
+        let saved = Config::load(&config.path).unwrap();
+        assert_eq!(saved.projects["inbox"].id, "6Jf8VQXxpwv56VQ7");
+        assert_eq!(saved.projects["Home"].color, Some(String::from("blue")));
+        assert_eq!(
+            saved.projects["Garden"].parent_id,
+            Some(String::from("6X7rM8997g3RQmvh"))
+        );
+        assert!(!saved.projects.contains_key("work"));
+    }
+
+    #[test]
+    fn sync_projects_qualifies_names_that_collide() {
+        let mut server = mockito::Server::new();
+        server
+            .mock("POST", "/sync/v9/sync")
+            .with_body(
+                json!({
+                    "sync_token": "abc",
+                    "full_sync": true,
+                    "projects": [
+                        {"id": "6X7rM8997g3RQmvh", "name": "Home"},
+                        {"id": "6X7rfFVPjhvv84XG", "name": "Admin", "parent_id": "6X7rM8997g3RQmvh"},
+                        {"id": "6X7rfEVP8hvv94XG", "name": "Work"},
+                        {"id": "6X7rfGVPjhvv84XH", "name": "Admin", "parent_id": "6X7rfEVP8hvv94XG"},
+                        {"id": "6X7rfHVPjhvv84XJ", "name": "Work"},
+                    ],
+                })
+                .to_string(),
+            )
+            .create();
+        let (config, _dir) = mock_config(&server.url());
+
+        let output = sync_projects(config.clone()).unwrap();
+
+        let saved = Config::load(&config.path).unwrap();
+        assert_eq!(saved.projects["Home/Admin"].id, "6X7rfFVPjhvv84XG");
+        assert_eq!(saved.projects["Work/Admin"].id, "6X7rfGVPjhvv84XH");
+        assert_eq!(
+            saved.projects["Work (6X7rfEVP8hvv94XG)"].id,
+            "6X7rfEVP8hvv94XG"
+        );
+        assert_eq!(
+            saved.projects["Work (6X7rfHVPjhvv84XJ)"].id,
+            "6X7rfHVPjhvv84XJ"
+        );
+        assert!(output.contains("Removed projects no longer in Todoist: inbox, work"));
+        assert!(output.contains("Admin is stored as Home/Admin"));
+    }
+
+    #[test]
+    fn complete_by_listing_number_and_id() {
+        let mut server = mockito::Server::new();
+        server
+            .mock("POST", "/sync/v9/sync")
+            .match_body(Matcher::PartialJson(json!({"sync_token": "*"})))
+            .with_body(
+                json!({
+                    "sync_token": "abc",
+                    "full_sync": true,
+                    "items": [item_json("10", "Low", 1), item_json("11", "Urgent", 4)],
+                })
+                .to_string(),
+            )
+            .create();
+        let close = server
+            .mock("POST", "/sync/v9/sync")
+            .match_body(Matcher::PartialJson(json!({
+                "commands": [
+                    {"type": "item_close", "args": {"id": "11"}},
+                    {"type": "item_close", "args": {"id": "2995104399"}},
+                ]
+            })))
+            .with_body_from_request(all_ok)
+            .create();
+        let (config, _dir) = mock_config(&server.url());
+
+        all_items(&config, "work", None, None, false).unwrap();
+        let config = Config::load(&config.path).unwrap();
+        assert_eq!(config.listing, vec!["10", "11"]);
+
+        assert_eq!(
+            complete_items(config.clone(), &[String::from("7")]),
+            Err(Error::Input(String::from(
+                "No task 7 in the last listing, which has 2 tasks"
+            )))
+        );
+        let targets = [String::from("2"), String::from("2995104399")];
+        let output = complete_items(config, &targets).unwrap();
+        close.assert();
+        assert!(output.starts_with("✓ Urgent\n✓ 2995104399"));
+    }
+
+    #[test]
+    fn goal_progress_can_be_turned_off() {
+        let mut server = mockito::Server::new();
+        server
+            .mock("POST", "/sync/v9/sync")
+            .match_body(Matcher::PartialJson(json!({
+                "commands": [{"type": "item_close", "args": {"id": "2995104311"}}]
+            })))
+            .with_body_from_request(all_ok)
+            .create();
+        let stats = server
+            .mock("POST", "/sync/v9/sync")
+            .match_body(Matcher::PartialJson(
+                json!({"resource_types": ["user", "stats"]}),
+            ))
+            .expect(0)
+            .create();
+        let (config, _dir) = mock_config(&server.url());
+        let config = Config {
+            goal_progress: Some(false),
+            ..config
+        };
+
+        let output = complete_items(config, &[String::from("2995104311")]).unwrap();
+
+        stats.assert();
+        assert_eq!(output, "✓ 2995104311");
+    }
+
+    #[test]
+    fn complete_recurring_reports_next_due_date() {
+        let mut server = mockito::Server::new();
+        let (config, _dir) = mock_config(&server.url());
+        let recurring = |days: i64| {
+            let mut item = item_json("2995104311", "Water plants", 1);
+            let date = time::today_date(&config) + Duration::days(days);
+            item["due"] = json!({
+                "date": date.format("%Y-%m-%d").to_string(),
+                "is_recurring": true,
+                "timezone": null,
+                "string": "every 2 days",
+                "lang": "en",
+            });
+            item
+        };
+        Cache {
+            items: vec![serde_json::from_value(recurring(0)).unwrap()],
+            ..Cache::default()
+        }
+        .save(&config)
+        .unwrap();
+        server
+            .mock("POST", "/sync/v9/sync")
+            .match_body(Matcher::PartialJson(json!({
+                "commands": [{"type": "item_close", "args": {"id": "2995104311"}}]
+            })))
+            .with_body_from_request(all_ok)
+            .create();
+        let sync = server
+            .mock("POST", "/sync/v9/sync")
+            .match_body(Matcher::PartialJson(
+                json!({"resource_types": request::RESOURCE_TYPES}),
+            ))
+            .with_body(
+                json!({"sync_token": "abc", "full_sync": true, "items": [recurring(2)]})
+                    .to_string(),
+            )
+            .create();
+        let next_due = (time::today_date(&config) + Duration::days(2)).format("%Y-%m-%d");
+
+        let output = complete_items(config, &[String::from("2995104311")]).unwrap();
+
+        sync.assert();
+        assert!(output.starts_with("✓ Water plants\n"));
+        assert!(output.contains(&format!(
+            "Water plants next due {} ↻ every 2 days",
+            next_due
+        )));
+    }
+
+    #[test]
+    fn rejected_close_of_recurring_task_is_not_looked_up() {
+        let mut server = mockito::Server::new();
+        let (config, _dir) = mock_config(&server.url());
+        let mut item = item_json("2995104311", "Water plants", 1);
+        item["due"] = json!({"date": "2024-03-01", "is_recurring": true, "timezone": null});
+        Cache {
+            items: vec![serde_json::from_value(item).unwrap()],
+            ..Cache::default()
+        }
+        .save(&config)
+        .unwrap();
+        server
+            .mock("POST", "/sync/v9/sync")
+            .match_body(Matcher::PartialJson(
+                json!({"commands": [{"type": "item_close"}]}),
+            ))
+            .with_body_from_request(|request| {
+                let body: serde_json::Value =
+                    serde_json::from_slice(request.body().unwrap()).unwrap();
+                let uuid = body["commands"][0]["uuid"].as_str().unwrap().to_owned();
+                json!({"sync_status": {uuid: {"error": "Item not found"}}})
+                    .to_string()
+                    .into()
+            })
+            .create();
+        let sync = server
+            .mock("POST", "/sync/v9/sync")
+            .match_body(Matcher::PartialJson(
+                json!({"resource_types": request::RESOURCE_TYPES}),
+            ))
+            .expect(0)
+            .create();
+
+        let output = complete_items(config, &[String::from("2995104311")]).unwrap();
+
+        sync.assert();
+        assert_eq!(output, "✗ Water plants: Item not found");
+    }
+
+    #[test]
+    fn modify_updates_fields_and_refetches() {
+        let mut server = mockito::Server::new();
+        let update = server
+            .mock("POST", "/sync/v9/sync")
+            .match_body(Matcher::PartialJson(json!({
+                "commands": [{
+                    "type": "item_update",
+                    "args": {"id": "2995104311", "content": "Renamed", "due": {"string": "tomorrow"}, "priority": 4}
+                }]
+            })))
+            .with_body_from_request(all_ok)
+            .create();
+        let get = server
+            .mock("POST", "/sync/v9/items/get")
+            .match_body(Matcher::PartialJson(json!({"item_id": "2995104311"})))
+            .with_body(json!({"item": item_json("2995104311", "Renamed", 4)}).to_string())
+            .create();
+        let (config, _dir) = mock_config(&server.url());
+        let changes = Changes {
+            content: Some(String::from("Renamed")),
+            due_string: Some(String::from("tomorrow")),
+            priority: Some(4),
+            ..Changes::default()
+        };
+
+        let output = modify_item(config, "2995104311", &changes).unwrap();
+
+        update.assert();
+        get.assert();
+        assert!(output.contains("Renamed"));
+    }
+
+    #[test]
+    fn subtasks_are_nested_and_skipped_by_next() {
+        let mut server = mockito::Server::new();
+        let mut subtask = item_json("11", "Draft", 1);
+        subtask["parent_id"] = json!("10");
+        server
+            .mock("POST", "/sync/v9/sync")
+            .with_body(
+                json!({
+                    "sync_token": "abc",
+                    "full_sync": true,
+                    "items": [item_json("10", "Write report", 4), subtask],
+                })
+                .to_string(),
+            )
+            .create();
+        let (config, _dir) = mock_config(&server.url());
+
+        let output = all_items(&config, "work", None, None, false).unwrap();
+        assert!(output.contains("1. Write report\n\n   2. Draft"));
+        let output = all_items(&config, "work", None, None, true).unwrap();
+        assert!(output.contains("2. Write report > Draft"));
+
+        let output = next_item(config, "work", None, None, 1, false).unwrap();
+        assert_eq!(output.trim(), "Draft");
+    }
+
+    #[test]
+    fn list_groups_by_section() {
+        let mut server = mockito::Server::new();
+        let mut sectioned = item_json("11", "Draft", 1);
+        sectioned["section_id"] = json!("7");
+        server
+            .mock("POST", "/sync/v9/sync")
+            .with_body(
+                json!({
+                    "sync_token": "abc",
+                    "full_sync": true,
+                    "items": [sectioned, item_json("10", "Plan", 1)],
+                    "sections": [{"id": "7", "name": "Writing", "project_id": "2", "section_order": 1}],
+                })
+                .to_string(),
+            )
+            .create();
+        let (config, _dir) = mock_config(&server.url());
+
+        let output = all_items(&config, "work", None, None, false).unwrap();
+        assert!(output.contains("1. Plan\n\nWriting\n\n2. Draft"));
+
+        let output = all_items(&config, "work", None, Some("writ"), false).unwrap();
+        assert!(output.contains("1. Draft"));
+        assert!(!output.contains("Plan"));
+
+        assert_eq!(
+            next_item(config, "work", None, Some("Editing"), 1, false),
+            Err(Error::Input(String::from(
+                "Section Editing not found in project"
+            )))
+        );
+    }
+
+    #[test]
+    fn upcoming_groups_by_date_across_projects() {
+        let mut server = mockito::Server::new();
+        let (config, _dir) = mock_config(&server.url());
+        let today = time::today_date(&config);
+        let due = |id: &str, content: &str, project_id: &str, days: i64| {
+            let mut item = item_json(id, content, 1);
+            item["project_id"] = json!(project_id);
+            item["due"] = json!({
+                "date": (today + Duration::days(days)).format("%Y-%m-%d").to_string(),
+                "is_recurring": false,
+                "timezone": null,
+            });
+            item
+        };
+        server
+            .mock("POST", "/sync/v9/sync")
+            .with_body(
+                json!({
+                    "sync_token": "abc",
+                    "full_sync": true,
+                    "items": [
+                        due("10", "Later", "2", 9),
+                        due("11", "Tomorrow", "1", 1),
+                        due("12", "Late", "2", -2),
+                        due("13", "Now", "2", 0),
+                    ],
+                })
+                .to_string(),
+            )
+            .create();
+
+        let output = agenda(&config, 7).unwrap();
+
+        assert!(output.starts_with("Overdue\n1. Late #work"));
+        assert!(output.contains("Today\n2. Now #work"));
+        assert!(output.contains("3. Tomorrow #inbox"));
+        assert!(!output.contains("Later"));
+    }
+
+    #[test]
+    fn filter_across_projects() {
+        let mut server = mockito::Server::new();
+        let mut inbox = item_json("12", "Buy milk", 1);
+        inbox["project_id"] = json!("1");
+        inbox["labels"] = json!(["errands"]);
+        server
+            .mock("POST", "/sync/v9/sync")
+            .with_body(
+                json!({
+                    "sync_token": "abc",
+                    "full_sync": true,
+                    "items": [item_json("10", "Plan", 1), item_json("11", "Ship", 4), inbox],
+                })
+                .to_string(),
+            )
+            .create();
+        let (config, _dir) = mock_config(&server.url());
+
+        let output = filter_items(&config, "p1 | @errands & !#work").unwrap();
+
+        assert!(output.contains("1. Ship"));
+        assert!(output.contains("2. Buy milk"));
+        assert!(!output.contains("Plan"));
+    }
+
+    #[test]
+    fn list_filters_by_label() {
+        let mut server = mockito::Server::new();
+        let mut errand = item_json("10", "Buy milk", 1);
+        errand["labels"] = json!(["Errands"]);
+        server
+            .mock("POST", "/sync/v9/sync")
+            .with_body(
+                json!({
+                    "sync_token": "abc",
+                    "full_sync": true,
+                    "items": [errand, item_json("11", "Write report", 4)],
+                })
+                .to_string(),
+            )
+            .create();
+        let (config, _dir) = mock_config(&server.url());
+
+        let output = all_items(&config, "work", Some("@errands"), None, false).unwrap();
+
+        assert!(output.contains("Buy milk @Errands"));
+        assert!(!output.contains("Write report"));
+    }
+
+    #[test]
+    fn labels_add_keeps_existing_labels() {
+        let mut server = mockito::Server::new();
+        let mut item = item_json("2995104311", "Call Bob", 1);
+        item["labels"] = json!(["phone"]);
+        let get = server
+            .mock("POST", "/sync/v9/items/get")
+            .with_body(json!({ "item": item }).to_string())
+            .create();
+        let update = server
+            .mock("POST", "/sync/v9/sync")
+            .match_body(Matcher::PartialJson(json!({
+                "commands": [{"type": "item_update", "args": {"id": "2995104311", "labels": ["phone", "waiting"]}}]
+            })))
+            .with_body_from_request(all_ok)
+            .create();
+        let (config, _dir) = mock_config(&server.url());
+
+        let targets = [String::from("2995104311")];
+        let labels = [String::from("@waiting"), String::from("phone")];
+        let output = update_labels(&config, &targets, &labels, false).unwrap();
+
+        get.assert();
+        update.assert();
+        assert_eq!(output, "✓ Call Bob");
+    }
+
+    #[test]
+    fn labels_are_matched_ignoring_case() {
+        let mut server = mockito::Server::new();
+        let mut item = item_json("2995104311", "Call Bob", 1);
+        item["labels"] = json!(["Phone", "Waiting"]);
+        server
+            .mock("POST", "/sync/v9/items/get")
+            .with_body(json!({ "item": item }).to_string())
+            .expect(2)
+            .create();
+        let remove = server
+            .mock("POST", "/sync/v9/sync")
+            .match_body(Matcher::PartialJson(json!({
+                "commands": [{"type": "item_update", "args": {"id": "2995104311", "labels": ["Waiting"]}}]
+            })))
+            .with_body_from_request(all_ok)
+            .create();
+        let (config, _dir) = mock_config(&server.url());
+        let targets = [String::from("2995104311")];
+
+        update_labels(&config, &targets, &[String::from("phone")], true).unwrap();
+        remove.assert();
+
+        let add = server
+            .mock("POST", "/sync/v9/sync")
+            .match_body(Matcher::PartialJson(json!({
+                "commands": [{"type": "item_update", "args": {"id": "2995104311", "labels": ["Phone", "Waiting"]}}]
+            })))
+            .with_body_from_request(all_ok)
+            .create();
+
+        update_labels(&config, &targets, &[String::from("waiting")], false).unwrap();
+        add.assert();
+    }
+
+    #[test]
+    fn postpone_keeps_time_and_recurrence() {
+        let mut server = mockito::Server::new();
+        let (config, _dir) = mock_config(&server.url());
+        let today = time::today_date(&config);
+        let mut item = item_json("2995104311", "Water plants", 1);
+        item["due"] = json!({
+            "date": format!("{}T09:00:00", (today - Duration::days(2)).format("%Y-%m-%d")),
+            "is_recurring": true,
+            "timezone": null,
+            "string": "every day at 9am",
+        });
+        let get = server
+            .mock("POST", "/sync/v9/items/get")
+            .with_body(json!({ "item": item }).to_string())
+            .create();
+        let update = server
+            .mock("POST", "/sync/v9/sync")
+            .match_body(Matcher::PartialJson(json!({
+                "commands": [{"type": "item_update", "args": {"id": "2995104311", "due": {
+                    "date": format!("{}T09:00:00", (today + Duration::days(1)).format("%Y-%m-%d")),
+                    "string": "every day at 9am",
+                }}}]
+            })))
+            .with_body_from_request(all_ok)
+            .create();
+
+        let output = postpone(&config, "2995104311", 1).unwrap();
+
+        get.assert();
+        update.assert();
+        assert!(output.starts_with("✓ Water plants → "));
+    }
+
+    #[test]
+    fn explain_needs_plain_output() {
+        let (config, _dir) = mock_config("http://localhost");
+        let config = Config {
+            output: Format::Json,
+            ..config
+        };
+
+        assert_eq!(
+            next_item(config, "work", None, None, 1, true),
+            Err(Error::Input(String::from(
+                "--explain only works with plain output"
+            )))
+        );
+    }
+
+    #[test]
+    fn summary_is_only_shown_when_every_change_succeeded() {
+        let mut server = mockito::Server::new();
+        let (config, _dir) = mock_config(&server.url());
+        let close = Command::item_close("2995104311");
+        let move_item = Command::item_move("2995104399", "2");
+        server
+            .mock("POST", "/sync/v9/sync")
+            .with_body(
+                json!({"sync_status": {
+                    &close.uuid: "ok",
+                    &move_item.uuid: {"error_code": 22, "error": "Item not found"},
+                }})
+                .to_string(),
+            )
+            .create();
+        let decisions = vec![
+            (String::from("Call Bob"), close),
+            (String::from("Buy milk"), move_item),
+        ];
+
+        let output = submit_and_summarize(&config, decisions, "Successfully sorted inbox").unwrap();
+
+        assert!(output.ends_with(&"1 of 2 changes failed".red().to_string()));
+        assert!(!output.contains("Successfully sorted inbox"));
+    }
+
+    #[test]
+    fn another_day_falls_back_to_natural_language() {
+        let (config, _dir) = mock_config("http://localhost");
+        let item: Item = serde_json::from_value(item_json("2995104311", "Call Bob", 1)).unwrap();
+
+        let (_, command) = date_decision(&config, &item, "the day after payday").unwrap();
+
+        assert_eq!(
+            command.args,
+            json!({"id": "2995104311", "due": {"string": "the day after payday"}})
+        );
+    }
+
+    #[test]
+    fn reschedule_recurring_needs_a_day() {
+        let mut server = mockito::Server::new();
+        let mut item = item_json("2995104311", "Water plants", 1);
+        item["due"] = json!({
+            "date": "2024-03-01",
+            "is_recurring": true,
+            "timezone": null,
+            "string": "every day",
+        });
+        server
+            .mock("POST", "/sync/v9/items/get")
+            .with_body(json!({ "item": item }).to_string())
+            .create();
+        let (config, _dir) = mock_config(&server.url());
+
+        assert_eq!(
+            reschedule(&config, "2995104311", "the day after payday"),
+            Err(Error::Input(String::from(
+                "Could not resolve the day after payday to a day, use i.e. tomorrow, friday or 2024-03-01 to keep Water plants recurring"
+            )))
+        );
+    }
+
+    #[test]
+    fn schedule_shows_time_blocks_and_overlaps() {
+        let mut server = mockito::Server::new();
+        let (config, _dir) = mock_config(&server.url());
+        let today = time::today_string(&config);
+        let block = |id: &str, content: &str, time: &str, minutes: u32| {
+            let mut item = item_json(id, content, 1);
+            item["due"] = json!({"date": format!("{}T{}:00", today, time), "is_recurring": false, "timezone": null});
+            item["duration"] = json!({"amount": minutes, "unit": "minute"});
+            item
+        };
+        let mut deadline = block("13", "Review", "14:00", 30);
+        deadline["deadline"] = json!({"date": "2999-01-01", "lang": "en"});
+        server
+            .mock("POST", "/sync/v9/sync")
+            .with_body(
+                json!({
+                    "sync_token": "abc",
+                    "full_sync": true,
+                    "items": [
+                        block("11", "Standup", "09:00", 30),
+                        block("12", "Planning", "09:15", 60),
+                        deadline,
+                    ],
+                })
+                .to_string(),
+            )
+            .create();
+
+        let output = scheduled_items(&config, "work").unwrap();
+
+        assert!(output.contains("Due: 09:00–09:30"));
+        assert!(output.contains("Due: 09:15–10:15"));
+        assert!(output.contains("Deadline: 2999-01-01"));
+        assert!(output.ends_with("⚠ Standup overlaps Planning"));
+    }
+
+    #[test]
+    fn sort_inbox_with_empty_inbox() {
+        let mut server = mockito::Server::new();
+        let mock = server
+            .mock("POST", "/sync/v9/sync")
+            .with_body(
+                json!({
+                    "sync_token": "abc",
+                    "full_sync": true,
+                    "items": [item_json("10", "Not in inbox", 1)],
+                })
+                .to_string(),
+            )
+            .create();
+        let (config, _dir) = mock_config(&server.url());
+
+        let output = sort_inbox(config).unwrap();
+
+        mock.assert();
+        assert_eq!(output, green_string("No tasks to sort in inbox"));
+    }
+
+    #[test]
+    fn add_item_to_inbox_only_quick_adds() {
+        let mut server = mockito::Server::new();
+        let quick_add = server
+            .mock("POST", "/sync/v9/quick/add")
+            .match_header("authorization", "Bearer alreadycreated")
+            .match_body(Matcher::PartialJson(json!({"text": "Buy milk"})))
+            .with_body(item_json("20", "Buy milk", 1).to_string())
+            .create();
+        let sync = server.mock("POST", "/sync/v9/sync").expect(0).create();
+        let (config, _dir) = mock_config(&server.url());
+
+        let output = add_item_to_project(config, "Buy milk", "inbox", &Changes::default()).unwrap();
+
+        quick_add.assert();
+        sync.assert();
+        assert_eq!(output, green_string("✓"));
+    }
+
+    #[test]
+    fn add_item_to_project_moves_item() {
+        let mut server = mockito::Server::new();
+        let quick_add = server
+            .mock("POST", "/sync/v9/quick/add")
+            .with_body(item_json("20", "Write report", 1).to_string())
+            .create();
+        let sync = server
+            .mock("POST", "/sync/v9/sync")
+            .match_body(Matcher::PartialJson(json!({
+                "commands": [{"type": "item_move", "args": {"id": "20", "project_id": "2"}}]
+            })))
+            .with_body_from_request(all_ok)
+            .create();
+        let (config, _dir) = mock_config(&server.url());
+
+        let output =
+            add_item_to_project(config, "Write report", "work", &Changes::default()).unwrap();
+
+        quick_add.assert();
+        sync.assert();
+        assert_eq!(output, green_string("✓"));
+    }
+
+    #[test]
+    fn add_item_offline_says_quick_add_is_skipped() {
+        let (config, _dir) = mock_config("http://127.0.0.1:9");
+
+        let output = add_item_to_project(
+            config.clone(),
+            "Buy milk tomorrow",
+            "inbox",
+            &Changes::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            output,
+            String::from(
+                "… Queued while offline, natural language (dates, #project, @label, p1) is not parsed"
+            )
+            .yellow()
+            .to_string()
+        );
+        assert_eq!(
+            queue::load(&config).unwrap()[0].args["content"],
+            "Buy milk tomorrow"
+        );
+    }
+
+    #[test]
+    fn add_item_reports_server_error() {
+        let mut server = mockito::Server::new();
+        server
+            .mock("POST", "/sync/v9/quick/add")
+            .with_status(500)
+            .create();
+        let (config, _dir) = mock_config(&server.url());
+
+        assert!(matches!(
+            add_item_to_project(config, "Buy milk", "inbox", &Changes::default()),
+            Err(Error::Http { status: 500, .. })
+        ));
+    }
+}
@@ -0,0 +1,478 @@
+use chrono::{DateTime, Utc};
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::commands::{Command, Status, SyncResponse};
+use crate::config::Config;
+use crate::error::Error;
+use crate::request;
+
+/// The Sync API accepts at most 100 commands per request
+const COMMAND_LIMIT: usize = 100;
+
+/// Path of the queue file, tod.queue in the same directory as tod.cfg
+pub fn path(config: &Config) -> String {
+    Path::new(&config.path)
+        .with_extension("queue")
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// A queued command that Todoist rejected when it was replayed, kept so that it is not lost
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct Rejected {
+    pub command: Command,
+    pub error: String,
+    pub rejected_at: DateTime<Utc>,
+}
+
+/// Path of the rejected commands, tod.rejected in the same directory as tod.cfg
+fn rejected_path(config: &Config) -> String {
+    Path::new(&config.path)
+        .with_extension("rejected")
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn load_rejected(config: &Config) -> Result<Vec<Rejected>, Error> {
+    let path = rejected_path(config);
+    if !Path::new(&path).exists() {
+        return Ok(Vec::new());
+    }
+
+    let json = fs::read_to_string(&path)
+        .map_err(|err| Error::Config(format!("Could not read {}: {}", path, err)))?;
+    serde_json::from_str(&json)
+        .map_err(|err| Error::Parse(format!("Could not parse {}: {}", path, err)))
+}
+
+/// Append commands to the rejected commands
+fn reject(config: &Config, rejected: Vec<Rejected>) -> Result<(), Error> {
+    if rejected.is_empty() {
+        return Ok(());
+    }
+
+    let path = rejected_path(config);
+    let rejected = [load_rejected(config)?, rejected].concat();
+    let json = serde_json::to_string_pretty(&rejected)
+        .map_err(|err| Error::Parse(format!("Could not convert to JSON: {}", err)))?;
+    fs::write(&path, json)
+        .map_err(|err| Error::Config(format!("Could not write {}: {}", path, err)))
+}
+
+/// Commands that have not been sent to Todoist yet, oldest first
+pub fn load(config: &Config) -> Result<Vec<Command>, Error> {
+    let path = path(config);
+    if !Path::new(&path).exists() {
+        return Ok(Vec::new());
+    }
+
+    let json = fs::read_to_string(&path)
+        .map_err(|err| Error::Config(format!("Could not read queue {}: {}", path, err)))?;
+    serde_json::from_str(&json)
+        .map_err(|err| Error::Parse(format!("Could not parse queue {}: {}", path, err)))
+}
+
+fn save(config: &Config, commands: &[Command]) -> Result<(), Error> {
+    let path = path(config);
+    if commands.is_empty() {
+        return match fs::remove_file(&path) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(Error::Config(format!(
+                "Could not remove queue {}: {}",
+                path, err
+            ))),
+            _ => Ok(()),
+        };
+    }
+
+    let json = serde_json::to_string_pretty(commands)
+        .map_err(|err| Error::Parse(format!("Could not convert queue to JSON: {}", err)))?;
+    fs::write(&path, json)
+        .map_err(|err| Error::Config(format!("Could not write queue {}: {}", path, err)))
+}
+
+/// Append commands to the end of the queue
+pub fn push(config: &Config, commands: Vec<Command>) -> Result<(), Error> {
+    let queued = [load(config)?, commands].concat();
+    save(config, &queued)?;
+
+    eprintln!(
+        "{}",
+        format!("No connection, queued for later ({} pending)", queued.len()).yellow()
+    );
+    Ok(())
+}
+
+/// Send a command after any that are already queued, queueing it when offline
+pub fn submit(config: &Config, command: Command) -> Result<(), Error> {
+    let uuid = command.uuid.clone();
+    let response = submit_all(config, vec![command])?;
+
+    match response.status(&uuid) {
+        Status::Failed(message) => Err(Error::Command(message)),
+        Status::Ok | Status::Queued => Ok(()),
+    }
+}
+
+/// Send commands after any that are already queued, as few requests as the command limit allows.
+/// Commands that could not be sent because there is no connection are queued.
+pub fn submit_all(config: &Config, commands: Vec<Command>) -> Result<SyncResponse, Error> {
+    let flushed = match flush(config) {
+        Ok(flushed) => flushed,
+        Err(Error::Network(_)) => return queue_commands(config, commands),
+        Err(err) => return Err(err),
+    };
+
+    // Commands can refer to items that were only just created by the flush
+    let commands = commands
+        .into_iter()
+        .map(|command| command.resolve_temp_ids(&flushed.temp_id_mapping))
+        .collect();
+    let sent = send(config, commands);
+    match sent.error {
+        None => Ok(sent.response),
+        Some(Error::Network(_)) => {
+            queue_commands(config, sent.unsent).map(|queued| sent.response.merge(queued))
+        }
+        Some(err) => Err(err),
+    }
+}
+
+/// Queue commands, with a response that reports each of them as queued
+fn queue_commands(config: &Config, commands: Vec<Command>) -> Result<SyncResponse, Error> {
+    let queued = commands
+        .iter()
+        .map(|command| command.uuid.clone())
+        .collect();
+    push(config, commands)?;
+    Ok(SyncResponse {
+        queued,
+        ..SyncResponse::default()
+    })
+}
+
+/// Replay queued commands in order and show the status of each on stderr.
+/// Only a network error is returned, so that a queued command cannot block later ones:
+/// commands the server rejects are moved to tod.rejected, and commands that failed
+/// on a server error stay queued for the next attempt.
+pub fn flush(config: &Config) -> Result<SyncResponse, Error> {
+    let queued = load(config)?;
+    if queued.is_empty() {
+        return Ok(SyncResponse::default());
+    }
+
+    let sent = send(config, queued.clone());
+    let unsent: HashSet<&String> = sent.unsent.iter().map(|command| &command.uuid).collect();
+    let mut rejected: Vec<Rejected> = Vec::new();
+    for command in queued
+        .iter()
+        .filter(|command| !unsent.contains(&command.uuid))
+    {
+        let status = sent.response.status(&command.uuid);
+        report_replayed(command, &status);
+        if let Status::Failed(error) = status {
+            rejected.push(rejection(command, &error));
+        }
+    }
+
+    let keep = match &sent.error {
+        None => Vec::new(),
+        Some(Error::Network(_)) => sent.unsent,
+        Some(err) if is_server_error(err) => {
+            eprintln!(
+                "{}",
+                format!(
+                    "Could not replay {} queued commands: {}",
+                    sent.unsent.len(),
+                    err
+                )
+                .yellow()
+            );
+            sent.unsent
+        }
+        Some(err) => {
+            for command in sent.unsent.iter() {
+                let status = Status::Failed(err.to_string());
+                report_replayed(command, &status);
+                rejected.push(rejection(command, &err.to_string()));
+            }
+            Vec::new()
+        }
+    };
+    save(config, &keep)?;
+    reject(config, rejected)?;
+
+    match sent.error {
+        Some(err @ Error::Network(_)) => Err(err),
+        _ => Ok(sent.response),
+    }
+}
+
+fn rejection(command: &Command, error: &str) -> Rejected {
+    Rejected {
+        command: command.clone(),
+        error: String::from(error),
+        rejected_at: Utc::now(),
+    }
+}
+
+/// Show the status of a replayed command, on stderr so that it does not mix with output
+fn report_replayed(command: &Command, status: &Status) {
+    let line = match status {
+        Status::Ok => format!("✓ Replayed {}", command.fmt()).green(),
+        Status::Queued => format!("… {} (queued)", command.fmt()).yellow(),
+        Status::Failed(message) => format!("✗ Rejected {}: {}", command.fmt(), message).red(),
+    };
+    eprintln!("{}", line);
+}
+
+/// Errors worth retrying later, the request may succeed once the server recovers
+fn is_server_error(err: &Error) -> bool {
+    matches!(err, Error::Http { status, .. } if *status == 429 || *status >= 500)
+}
+
+/// Result of sending commands in batches
+struct Sent {
+    response: SyncResponse,
+    unsent: Vec<Command>,
+    error: Option<Error>,
+}
+
+/// Post commands in batches of at most COMMAND_LIMIT, stopping at the first failed request.
+/// Temp ids are resolved between batches so later commands can refer to items added earlier.
+fn send(config: &Config, commands: Vec<Command>) -> Sent {
+    let mut remaining = commands;
+    let mut response = SyncResponse::default();
+
+    while !remaining.is_empty() {
+        let batch: Vec<Command> = remaining
+            .drain(..remaining.len().min(COMMAND_LIMIT))
+            .collect();
+
+        match request::execute_commands(config, &batch) {
+            Ok(batch_response) => {
+                response = response.merge(batch_response);
+                remaining = remaining
+                    .into_iter()
+                    .map(|command| command.resolve_temp_ids(&response.temp_id_mapping))
+                    .collect();
+            }
+            Err(err) => {
+                return Sent {
+                    response,
+                    unsent: [batch, remaining].concat(),
+                    error: Some(err),
+                }
+            }
+        }
+    }
+
+    Sent {
+        response,
+        unsent: Vec::new(),
+        error: None,
+    }
+}
+
+/// List the commands still waiting to be sent, and the queued commands Todoist rejected
+pub fn pending(config: &Config) -> Result<String, Error> {
+    let commands = load(config)?;
+    let rejected = load_rejected(config)?;
+    if commands.is_empty() && rejected.is_empty() {
+        return Ok(String::from("No pending commands").green().to_string());
+    }
+
+    let mut buffer = format!("Pending commands ({})", commands.len())
+        .green()
+        .to_string();
+    for command in commands {
+        buffer.push_str("\n - ");
+        buffer.push_str(&command.fmt());
+    }
+    if !rejected.is_empty() {
+        buffer.push_str(&format!(
+            "\n\n{}",
+            format!(
+                "Rejected commands ({}), kept in {}",
+                rejected.len(),
+                rejected_path(config)
+            )
+            .red()
+        ));
+        for rejected in rejected {
+            buffer.push_str(&format!(
+                "\n - {}: {}",
+                rejected.command.fmt(),
+                rejected.error
+            ));
+        }
+    }
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::tests::all_ok;
+    use crate::config::tests::mock_config;
+    use mockito::Matcher;
+    use serde_json::json;
+
+    #[test]
+    fn offline_commands_are_replayed_with_temp_ids() {
+        let (offline, _dir) = mock_config("http://127.0.0.1:9");
+        let add = Command::item_add("Buy milk");
+        let temp_id = add.temp_id.clone().unwrap();
+
+        submit(&offline, add).unwrap();
+        submit(&offline, Command::item_close(&temp_id)).unwrap();
+        assert_eq!(load(&offline).unwrap().len(), 2);
+
+        let mut server = mockito::Server::new();
+        let mock = server
+            .mock("POST", "/sync/v9/sync")
+            .match_body(Matcher::PartialJson(json!({
+                "commands": [{"type": "item_add"}, {"type": "item_close", "args": {"id": temp_id}}]
+            })))
+            .with_body_from_request(move |request| {
+                let body: serde_json::Value =
+                    serde_json::from_slice(request.body().unwrap()).unwrap();
+                let uuids: Vec<&str> = body["commands"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|command| command["uuid"].as_str().unwrap())
+                    .collect();
+                json!({
+                    "sync_status": {uuids[0]: "ok", uuids[1]: "ok"},
+                    "temp_id_mapping": {&temp_id: "42"},
+                })
+                .to_string()
+                .into()
+            })
+            .create();
+        let online = Config {
+            todoist_url: Some(server.url()),
+            ..offline
+        };
+
+        let queued = load(&online).unwrap();
+        let response = flush(&online).unwrap();
+        mock.assert();
+        assert!(queued
+            .iter()
+            .all(|command| response.status(&command.uuid) == Status::Ok));
+        assert!(load(&online).unwrap().is_empty());
+    }
+
+    #[test]
+    fn new_commands_use_ids_created_by_the_flush() {
+        let (offline, _dir) = mock_config("http://127.0.0.1:9");
+        let add = Command::item_add("Buy milk");
+        let temp_id = add.temp_id.clone().unwrap();
+        submit(&offline, add.clone()).unwrap();
+
+        let mut server = mockito::Server::new();
+        server
+            .mock("POST", "/sync/v9/sync")
+            .match_body(Matcher::PartialJson(
+                json!({"commands": [{"type": "item_add"}]}),
+            ))
+            .with_body(
+                json!({
+                    "sync_status": {&add.uuid: "ok"},
+                    "temp_id_mapping": {&temp_id: "42"},
+                })
+                .to_string(),
+            )
+            .create();
+        let moved = server
+            .mock("POST", "/sync/v9/sync")
+            .match_body(Matcher::PartialJson(json!({
+                "commands": [{"type": "item_move", "args": {"id": "42", "project_id": "2"}}]
+            })))
+            .with_body_from_request(all_ok)
+            .create();
+        let online = Config {
+            todoist_url: Some(server.url()),
+            ..offline
+        };
+
+        assert_eq!(submit(&online, Command::item_move(&temp_id, "2")), Ok(()));
+        moved.assert();
+    }
+
+    #[test]
+    fn rejected_queued_commands_are_kept_and_do_not_block() {
+        let (offline, _dir) = mock_config("http://127.0.0.1:9");
+        let close = Command::item_close("1");
+        submit(&offline, close.clone()).unwrap();
+
+        let mut server = mockito::Server::new();
+        server
+            .mock("POST", "/sync/v9/sync")
+            .match_body(Matcher::PartialJson(json!({"commands": [{"args": {"id": "1"}}]})))
+            .with_body(
+                json!({"sync_status": {&close.uuid: {"error_code": 22, "error": "Item not found"}}})
+                    .to_string(),
+            )
+            .create();
+        let update = Command::item_update_priority("2", 4);
+        server
+            .mock("POST", "/sync/v9/sync")
+            .match_body(Matcher::PartialJson(
+                json!({"commands": [{"args": {"id": "2"}}]}),
+            ))
+            .with_body(json!({"sync_status": {&update.uuid: "ok"}}).to_string())
+            .create();
+        let online = Config {
+            todoist_url: Some(server.url()),
+            ..offline
+        };
+
+        assert_eq!(submit(&online, update), Ok(()));
+        assert!(load(&online).unwrap().is_empty());
+        let rejected = load_rejected(&online).unwrap();
+        assert_eq!(rejected[0].command, close);
+        assert_eq!(rejected[0].error, "Item not found");
+    }
+
+    #[test]
+    fn large_submissions_respect_command_limit() {
+        let mut server = mockito::Server::new();
+        let mock = server
+            .mock("POST", "/sync/v9/sync")
+            .with_body_from_request(all_ok)
+            .expect(2)
+            .create();
+        let (config, _dir) = mock_config(&server.url());
+        let commands = (0..150)
+            .map(|id| Command::item_close(&id.to_string()))
+            .collect();
+
+        submit_all(&config, commands).unwrap();
+        mock.assert();
+    }
+
+    #[test]
+    fn rejected_command_is_an_error() {
+        let mut server = mockito::Server::new();
+        let command = Command::item_close("1");
+        server
+            .mock("POST", "/sync/v9/sync")
+            .with_body(
+                json!({"sync_status": {&command.uuid: {"error_code": 22, "error": "Item not found"}}})
+                    .to_string(),
+            )
+            .create();
+        let (config, _dir) = mock_config(&server.url());
+
+        assert_eq!(
+            submit(&config, command),
+            Err(Error::Command(String::from("Item not found")))
+        );
+    }
+}
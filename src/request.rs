@@ -1,24 +1,49 @@
-use reqwest::blocking::Client;
+use chrono::{DateTime, Utc};
+use reqwest::blocking::{Client, Response};
 use reqwest::header::AUTHORIZATION;
 use reqwest::header::CONTENT_TYPE;
 use reqwest::header::USER_AGENT;
 use serde::Deserialize;
 use serde_json::json;
-use uuid::Uuid;
 
+use crate::commands::{Command, Status, SyncResponse};
+use crate::completed::CompletedItem;
 use crate::config::Config;
+use crate::error::Error;
 use crate::items::Item;
-use crate::{items, projects};
+use crate::{commands, items, projects, queue};
 
 // TODOIST URLS
 const QUICK_ADD_URL: &str = "/sync/v9/quick/add";
-const PROJECT_DATA_URL: &str = "/sync/v9/projects/get_data";
 const SYNC_URL: &str = "/sync/v9/sync";
-const REST_V2_TASKS_URL: &str = "/rest/v2/tasks/";
+const ITEM_URL: &str = "/sync/v9/items/get";
+const COMPLETED_URL: &str = "/sync/v9/completed/get_all";
+
+/// Most completed items returned in one page
+const COMPLETED_LIMIT: usize = 200;
+
+/// Resources fetched by sync, a cache built from a different list needs a full sync
+pub const RESOURCE_TYPES: [&str; 4] = ["items", "projects", "labels", "sections"];
 
 // CRATES.IO URLS
 const VERSIONS_URL: &str = "/v1/crates/tod/versions";
 
+// BASE URLS, can be overridden in config or with environment variables
+const TODOIST_BASE_URL: &str = "https://api.todoist.com";
+const CARGO_BASE_URL: &str = "https://crates.io/api";
+const TODOIST_URL_ENV: &str = "TOD_TODOIST_URL";
+const CARGO_URL_ENV: &str = "TOD_CARGO_URL";
+
+#[derive(Deserialize)]
+struct ItemResponse {
+    item: Item,
+}
+
+#[derive(Deserialize)]
+struct CompletedResponse {
+    items: Vec<CompletedItem>,
+}
+
 #[derive(Deserialize)]
 struct CargoResponse {
     versions: Vec<Version>,
@@ -29,51 +54,138 @@ struct Version {
     num: String,
 }
 
-/// Add a new item to the inbox with natural language support
-pub fn add_item_to_inbox(config: &Config, task: &str) -> Result<Item, String> {
+/// Add a new item to the inbox with natural language support.
+/// When offline the text is queued as a plain item_add without natural language parsing,
+/// the returned item has a temp id and the status is Queued.
+pub fn add_item_to_inbox(config: &Config, task: &str) -> Result<(Item, Status), Error> {
     let url = String::from(QUICK_ADD_URL);
     let body = json!({"text": task, "auto_reminder": true});
 
-    let json = post_todoist_sync(config.token.clone(), url, body)?;
-    items::json_to_item(json)
+    match queue::flush(config).and_then(|_| post_todoist_sync(config, url, body)) {
+        Ok(json) => Ok((items::json_to_item(json)?, Status::Ok)),
+        Err(Error::Network(_)) => {
+            let command = Command::item_add(task);
+            let item = Item {
+                id: command.temp_id.clone().unwrap_or_default(),
+                project_id: String::new(),
+                content: String::from(task),
+                priority: 1,
+                checked: false,
+                description: String::new(),
+                due: None,
+                is_deleted: false,
+                labels: Vec::new(),
+                parent_id: None,
+                child_order: 0,
+                section_id: None,
+                duration: None,
+                deadline: None,
+            };
+            queue::push(config, vec![command])?;
+            Ok((item, Status::Queued))
+        }
+        Err(err) => Err(err),
+    }
 }
 
-/// Get a vector of all items for a project
-pub fn items_for_project(config: &Config, project_id: &str) -> Result<Vec<Item>, String> {
-    let url = String::from(PROJECT_DATA_URL);
-    let body = json!({ "project_id": project_id });
-    let json = post_todoist_sync(config.token.clone(), url, body)?;
-    items::json_to_items(json)
+/// Fetch items and projects changed since the sync token, "*" fetches everything
+pub fn sync(config: &Config, sync_token: &str) -> Result<String, Error> {
+    let url = String::from(SYNC_URL);
+    let body = json!({"sync_token": sync_token, "resource_types": RESOURCE_TYPES});
+    post_todoist_sync(config, url, body)
+}
+
+/// Fetch karma, goals and completion stats of the user
+pub fn get_user_stats(config: &Config) -> Result<String, Error> {
+    let url = String::from(SYNC_URL);
+    let body = json!({"sync_token": "*", "resource_types": ["user", "stats"]});
+    post_todoist_sync(config, url, body)
+}
+
+/// Fetch a single item by ID
+pub fn get_item(config: &Config, id: &str) -> Result<Item, Error> {
+    let url = String::from(ITEM_URL);
+    let body = json!({ "item_id": id, "all_data": false });
+
+    let json = post_todoist_sync(config, url, body)?;
+    serde_json::from_str::<ItemResponse>(&json)
+        .map(|response| response.item)
+        .map_err(|err| Error::Parse(format!("Could not parse response for item: {:?}", err)))
+}
+
+/// Fetch items completed between since and until, optionally in one project, newest first
+pub fn get_completed_items(
+    config: &Config,
+    project_id: Option<&str>,
+    since: DateTime<Utc>,
+    until: Option<DateTime<Utc>>,
+) -> Result<Vec<CompletedItem>, Error> {
+    let mut items: Vec<CompletedItem> = Vec::new();
+    loop {
+        let mut body = json!({
+            "since": since.format("%Y-%m-%dT%H:%M").to_string(),
+            "limit": COMPLETED_LIMIT,
+            "offset": items.len(),
+        });
+        if let Some(project_id) = project_id {
+            body["project_id"] = json!(project_id);
+        }
+        if let Some(until) = until {
+            body["until"] = json!(until.format("%Y-%m-%dT%H:%M").to_string());
+        }
+
+        let json = post_todoist_sync(config, String::from(COMPLETED_URL), body)?;
+        let page = serde_json::from_str::<CompletedResponse>(&json)
+            .map_err(|err| Error::Parse(format!("Could not parse completed items: {:?}", err)))?
+            .items;
+        let last_page = page.len() < COMPLETED_LIMIT;
+        items.extend(page);
+        if last_page {
+            return Ok(items);
+        }
+    }
+}
+
+/// Post a list of commands to the sync endpoint
+pub fn execute_commands(config: &Config, commands: &[Command]) -> Result<SyncResponse, Error> {
+    let url = String::from(SYNC_URL);
+    let body = json!({ "commands": commands });
+
+    let json = post_todoist_sync(config, url, body)?;
+    commands::json_to_sync_response(json)
 }
 
 /// Move an item to a different project
-pub fn move_item(config: Config, item: Item, project_name: &str) -> Result<String, String> {
+pub fn move_item(config: Config, item: Item, project_name: &str) -> Result<String, Error> {
     let project_id = projects::project_id(&config, project_name)?;
-    let body = json!({"commands": [{"type": "item_move", "uuid": new_uuid(), "args": {"id": item.id, "project_id": project_id}}]});
-    let url = String::from(SYNC_URL);
 
-    post_todoist_sync(config.token, url, body)?;
+    queue::submit(&config, Command::item_move(&item.id, &project_id))?;
     Ok(String::from("✓"))
 }
 
 /// Update the priority of an item by ID
-pub fn update_item_priority(config: Config, item: Item, priority: u8) -> Result<String, String> {
-    let body = json!({ "priority": priority });
-    let url = format!("{}{}", REST_V2_TASKS_URL, item.id);
-
-    post_todoist_rest(config.token, url, body)?;
+pub fn update_item_priority(config: Config, item: Item, priority: u8) -> Result<String, Error> {
+    queue::submit(&config, Command::item_update_priority(&item.id, priority))?;
     // Does not pass back an item
     Ok(String::from("✓"))
 }
 
-/// Complete the last item returned by "next item"
-pub fn complete_item(config: Config) -> Result<String, String> {
-    let body = json!({"commands": [{"type": "item_close", "uuid": new_uuid(), "temp_id": new_uuid(), "args": {"id": config.next_id}}]});
-    let url = String::from(SYNC_URL);
+/// Update the due date of an item with natural language
+pub fn update_item_due(config: Config, item: Item, due_string: &str) -> Result<String, Error> {
+    queue::submit(&config, Command::item_update_due(&item.id, due_string))?;
+    // Does not pass back an item
+    Ok(String::from("✓"))
+}
 
-    post_todoist_sync(config.token.clone(), url, body)?;
+/// Complete an item, taking it out of the focus queue only when it is there
+pub fn close_item(config: Config, item: Item) -> Result<String, Error> {
+    queue::submit(&config, Command::item_close(&item.id))?;
 
-    config.clear_next_id().save()?;
+    // Reload so that focus changes made since config was read are kept
+    let config = Config::load(&config.path)?;
+    if config.focus.contains(&item.id) || config.next_id.as_ref() == Some(&item.id) {
+        config.remove_from_focus(&[item.id]).save()?;
+    }
 
     // Does not pass back an item
     Ok(String::from("✓"))
@@ -81,79 +193,76 @@ pub fn complete_item(config: Config) -> Result<String, String> {
 
 /// Post to Todoist via sync API
 fn post_todoist_sync(
-    token: String,
+    config: &Config,
     url: String,
     body: serde_json::Value,
-) -> Result<String, String> {
-    let todoist_url: &str = "https://api.todoist.com";
-
-    let request_url = format!("{}{}", todoist_url, url);
+) -> Result<String, Error> {
+    let request_url = format!("{}{}", todoist_url(config), url);
 
     let response = Client::new()
         .post(request_url)
         .header(CONTENT_TYPE, "application/json")
-        .header(AUTHORIZATION, format!("Bearer {}", token))
+        .header(AUTHORIZATION, format!("Bearer {}", config.token))
         .json(&body)
         .send()
-        .or(Err("Did not get response from server"))?;
+        .map_err(network_error)?;
 
-    if response.status().is_success() {
-        Ok(response.text().or(Err("Could not read response text"))?)
-    } else {
-        Err(format!("Error: {:#?}", response.text()))
-    }
+    response_text(response)
 }
 
-/// Post to Todoist via REST api
-fn post_todoist_rest(
-    token: String,
-    url: String,
-    body: serde_json::Value,
-) -> Result<String, String> {
-    let todoist_url: &str = "https://api.todoist.com";
-
-    let request_url = format!("{}{}", todoist_url, url);
-    let authorization: &str = &format!("Bearer {}", token);
+/// Get latest version number from Cargo.io
+pub fn get_latest_version(config: &Config) -> Result<String, Error> {
+    let request_url = format!("{}{}", cargo_url(config), VERSIONS_URL);
 
     let response = Client::new()
-        .post(request_url)
-        .header(CONTENT_TYPE, "application/json")
-        .header(AUTHORIZATION, authorization)
-        .header("X-Request-Id", new_uuid())
-        .json(&body)
+        .get(request_url)
+        .header(USER_AGENT, "Tod")
         .send()
-        .or(Err("Did not get response from server"))?;
+        .map_err(network_error)?;
 
-    if response.status().is_success() {
-        Ok(response.text().or(Err("Could not read response text"))?)
+    let cr: CargoResponse = serde_json::from_str(&response_text(response)?)
+        .map_err(|err| Error::Parse(format!("Could not parse CargoResponse: {}", err)))?;
+    cr.versions
+        .first()
+        .map(|version| version.num.clone())
+        .ok_or_else(|| Error::Parse(String::from("No versions in CargoResponse")))
+}
+
+/// Read the body of a response, keeping the status code when it is not a success
+fn response_text(response: Response) -> Result<String, Error> {
+    let status = response.status();
+    let body = response.text().map_err(network_error)?;
+
+    if status.is_success() {
+        Ok(body)
     } else {
-        Err(format!("Error: {:#?}", response.text()))
+        Err(Error::Http {
+            status: status.as_u16(),
+            body,
+        })
     }
 }
 
-/// Get latest version number from Cargo.io
-pub fn get_latest_version() -> Result<String, String> {
-    let cargo_url: &str = "https://crates.io/api";
-
-    let request_url = format!("{}{}", cargo_url, VERSIONS_URL);
+fn network_error(err: reqwest::Error) -> Error {
+    Error::Network(format!("Did not get response from server, {}", err))
+}
 
-    let response = Client::new()
-        .get(request_url)
-        .header(USER_AGENT, "Tod")
-        .send()
-        .or(Err("Did not get response from server"))?;
+/// Todoist base URL, from the environment, then config, then the default
+fn todoist_url(config: &Config) -> String {
+    base_url(TODOIST_URL_ENV, &config.todoist_url, TODOIST_BASE_URL)
+}
 
-    if response.status().is_success() {
-        let cr: CargoResponse =
-            serde_json::from_str(&response.text().or(Err("Could not read response text"))?)
-                .or(Err("Could not serialize to CargoResponse"))?;
-        Ok(cr.versions.first().unwrap().num.clone())
-    } else {
-        Err(format!("Error: {:#?}", response.text()))
-    }
+/// Crates.io base URL, from the environment, then config, then the default
+fn cargo_url(config: &Config) -> String {
+    base_url(CARGO_URL_ENV, &config.cargo_url, CARGO_BASE_URL)
 }
 
-/// Create a new UUID, required for Todoist API
-fn new_uuid() -> String {
-    Uuid::new_v4().to_string()
+fn base_url(env_var: &str, configured: &Option<String>, default: &str) -> String {
+    let url = match (std::env::var(env_var), configured) {
+        (Ok(url), _) if !url.is_empty() => url,
+        (_, Some(url)) => url.to_owned(),
+        _ => String::from(default),
+    };
+
+    url.trim_end_matches('/').to_owned()
 }
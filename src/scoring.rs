@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+
+/// Weights that decide which task comes next, stored in config as named profiles.
+/// Missing fields take the default weights.
+#[derive(Clone, Serialize, Deserialize, Eq, PartialEq, Debug)]
+#[serde(default)]
+pub struct Scoring {
+    pub no_date: u32,
+    pub due_today: u32,
+    pub overdue: u32,
+    /// Given to tasks with a time within due_soon_minutes of now
+    pub due_soon: u32,
+    pub due_soon_minutes: i64,
+    pub not_recurring: u32,
+    pub invalid_date: u32,
+    pub p1: u32,
+    pub p2: u32,
+    pub p3: u32,
+    pub p4: u32,
+}
+
+impl Default for Scoring {
+    fn default() -> Scoring {
+        Scoring {
+            no_date: 80,
+            due_today: 100,
+            overdue: 150,
+            due_soon: 200,
+            due_soon_minutes: 15,
+            not_recurring: 50,
+            invalid_date: 50,
+            p1: 4,
+            p2: 3,
+            p3: 1,
+            p4: 2,
+        }
+    }
+}
+
+impl Scoring {
+    /// Weight of a priority as stored by the API, where p1 is 4
+    pub fn priority(&self, priority: u8) -> u32 {
+        match priority {
+            4 => self.p1,
+            3 => self.p2,
+            2 => self.p3,
+            _ => self.p4,
+        }
+    }
+}
+
+/// The points given to a task and the reason for each
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Score {
+    pub parts: Vec<(String, u32)>,
+}
+
+impl Score {
+    pub fn add(&mut self, reason: &str, points: u32) {
+        self.parts.push((String::from(reason), points));
+    }
+
+    pub fn total(&self) -> u32 {
+        self.parts.iter().map(|(_, points)| points).sum()
+    }
+
+    /// Breakdown such as "due today 100 + not recurring 50 + p1 4"
+    pub fn explain(&self) -> String {
+        self.parts
+            .iter()
+            .filter(|(_, points)| *points > 0)
+            .map(|(reason, points)| format!("{} {}", reason, points))
+            .collect::<Vec<String>>()
+            .join(" + ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_profile_uses_default_weights() {
+        let scoring: Scoring = serde_json::from_str(r#"{"no_date": 300, "p1": 50}"#).unwrap();
+
+        assert_eq!(
+            scoring,
+            Scoring {
+                no_date: 300,
+                p1: 50,
+                ..Scoring::default()
+            }
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use crate::config::Config;
+use crate::error::Error;
 use chrono::offset::{TimeZone, Utc};
-use chrono::{DateTime, NaiveDate};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, Weekday};
 use chrono_tz::{Tz, TZ_VARIANTS};
 
 pub fn now(config: &Config) -> DateTime<Tz> {
@@ -48,16 +49,22 @@ pub fn format_datetime(datetime: &DateTime<Tz>, config: &Config) -> String {
 }
 
 /// Parse DateTime
-pub fn datetime_from_str(str: &str, timezone: Tz) -> Result<DateTime<Tz>, String> {
+pub fn datetime_from_str(str: &str, timezone: Tz) -> Result<DateTime<Tz>, Error> {
     let datetime = match str.len() {
         19 => timezone
-            .datetime_from_str(str, "%Y-%m-%dT%H:%M:%S")
-            .expect("could not parse DateTime"),
+            .from_local_datetime(
+                &NaiveDateTime::parse_from_str(str, "%Y-%m-%dT%H:%M:%S")
+                    .map_err(|_| parse_error("could not parse DateTime"))?,
+            )
+            .earliest()
+            .ok_or(parse_error("could not parse DateTime"))?,
         20 => Utc
-            .datetime_from_str(str, "%Y-%m-%dT%H:%M:%SZ")
-            .expect("could not parse DateTime")
+            .from_utc_datetime(
+                &NaiveDateTime::parse_from_str(str, "%Y-%m-%dT%H:%M:%SZ")
+                    .map_err(|_| parse_error("could not parse DateTime"))?,
+            )
             .with_timezone(&Tz::UTC),
-        _ => return Err(format!("cannot parse DateTime: {}", str)),
+        _ => return Err(Error::Parse(format!("cannot parse DateTime: {}", str))),
     };
 
     Ok(datetime)
@@ -71,31 +78,104 @@ pub fn timezone_from_str(timezone_string: &Option<String>) -> Tz {
 }
 
 /// Parse Date
-pub fn date_from_str(str: &str, timezone: Tz) -> Result<NaiveDate, String> {
+pub fn date_from_str(str: &str, timezone: Tz) -> Result<NaiveDate, Error> {
     let date = match str.len() {
-        10 => NaiveDate::parse_from_str(str, "%Y-%m-%d").or(Err("could not parse Date"))?,
+        10 => NaiveDate::parse_from_str(str, "%Y-%m-%d")
+            .or(Err(parse_error("could not parse Date")))?,
         19 => timezone
-            .datetime_from_str(str, "%Y-%m-%dT%H:%M:%S")
-            .or(Err("could not parse DateTime"))?
+            .from_local_datetime(
+                &NaiveDateTime::parse_from_str(str, "%Y-%m-%dT%H:%M:%S")
+                    .or(Err(parse_error("could not parse DateTime")))?,
+            )
+            .earliest()
+            .ok_or(parse_error("could not parse DateTime"))?
             .date_naive(),
 
         20 => timezone
-            .datetime_from_str(str, "%Y-%m-%dT%H:%M:%SZ")
-            .or(Err("could not parse DateTime"))?
+            .from_local_datetime(
+                &NaiveDateTime::parse_from_str(str, "%Y-%m-%dT%H:%M:%SZ")
+                    .or(Err(parse_error("could not parse DateTime")))?,
+            )
+            .earliest()
+            .ok_or(parse_error("could not parse DateTime"))?
             .date_naive(),
-        _ => return Err(format!("cannot parse NaiveDate, unknown length: {}", str)),
+        _ => {
+            return Err(Error::Parse(format!(
+                "cannot parse NaiveDate, unknown length: {}",
+                str
+            )))
+        }
     };
 
     Ok(date)
 }
 
+/// Resolve a simple date to a day: today, tomorrow, next week, a weekday,
+/// in 3 days or 2024-03-01. Returns None for anything else
+pub fn parse_date(input: &str, config: &Config) -> Option<NaiveDate> {
+    let today = today_date(config);
+    let input = input.trim().to_lowercase();
+    match input.as_str() {
+        "today" | "tod" => return Some(today),
+        "tomorrow" | "tom" => return Some(today + Duration::days(1)),
+        "next week" => return Some(next_weekday(today, Weekday::Mon)),
+        _ => (),
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(&input, "%Y-%m-%d") {
+        return Some(date);
+    }
+    if let Ok(weekday) = input.trim_start_matches("next ").parse::<Weekday>() {
+        return Some(next_weekday(today, weekday));
+    }
+    let days = input.trim_start_matches("in ");
+    days.strip_prefix('+')
+        .or_else(|| days.strip_suffix(" days"))
+        .or_else(|| days.strip_suffix(" day"))
+        .and_then(|days| days.trim().parse::<i64>().ok())
+        .map(|days| today + Duration::days(days))
+}
+
+/// The first day after date that falls on weekday
+fn next_weekday(date: NaiveDate, weekday: Weekday) -> NaiveDate {
+    let days = (7 + weekday.num_days_from_monday() - date.weekday().num_days_from_monday()) % 7;
+    date + Duration::days(if days == 0 { 7 } else { days as i64 })
+}
+
+fn parse_error(message: &str) -> Error {
+    Error::Parse(String::from(message))
+}
+
 pub fn list_timezones() {
-    println!("Timezones:");
+    eprintln!("Timezones:");
     for (num, tz) in TZ_VARIANTS.iter().enumerate() {
-        println!("{}: {}", num, tz);
+        eprintln!("{}: {}", num, tz);
     }
 }
 
 pub fn get_timezone(num: usize) -> String {
     TZ_VARIANTS[num].to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_datetimes_are_parse_errors() {
+        let parse_failure = Err(parse_error("could not parse DateTime"));
+
+        assert_eq!(
+            datetime_from_str("2024-13-01T09:00:00", Tz::UTC),
+            parse_failure
+        );
+        assert_eq!(
+            datetime_from_str("2024-03-01T09:00:0XZ", Tz::UTC),
+            parse_failure
+        );
+        // Clocks go from 02:00 to 03:00 that night
+        assert_eq!(
+            datetime_from_str("2024-03-10T02:30:00", chrono_tz::America::New_York),
+            parse_failure
+        );
+    }
+}
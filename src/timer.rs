@@ -0,0 +1,329 @@
+use chrono::{DateTime, Duration, Utc};
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{IsTerminal, Write};
+use std::path::Path;
+
+use crate::cache::Cache;
+use crate::config::Config;
+use crate::error::Error;
+use crate::projects;
+
+/// Length of a pomodoro when pomodoro_minutes is not set in config
+const DEFAULT_POMODORO_MINUTES: u32 = 25;
+
+/// Work sessions on tasks, serialized as json next to tod.cfg
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Eq, PartialEq)]
+struct TimeLog {
+    running: Option<Session>,
+    sessions: Vec<Session>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+struct Session {
+    item_id: String,
+    content: String,
+    project_id: String,
+    started_at: DateTime<Utc>,
+    stopped_at: Option<DateTime<Utc>>,
+    /// Minutes of the pomodoro when the session was started as one
+    pomodoro_minutes: Option<u32>,
+}
+
+impl Session {
+    /// Time worked, up to now for a running session
+    fn duration(&self, now: DateTime<Utc>) -> Duration {
+        self.stopped_at.unwrap_or(now) - self.started_at
+    }
+}
+
+/// Path of the time log, tod.timelog in the same directory as tod.cfg
+fn path(config: &Config) -> String {
+    Path::new(&config.path)
+        .with_extension("timelog")
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn load(config: &Config) -> Result<TimeLog, Error> {
+    let path = path(config);
+    if !Path::new(&path).exists() {
+        return Ok(TimeLog::default());
+    }
+
+    let json = fs::read_to_string(&path)
+        .map_err(|err| Error::Config(format!("Could not read {}: {}", path, err)))?;
+    serde_json::from_str(&json)
+        .map_err(|err| Error::Parse(format!("Could not parse {}: {}", path, err)))
+}
+
+fn save(config: &Config, log: &TimeLog) -> Result<(), Error> {
+    let path = path(config);
+    let json = serde_json::to_string_pretty(log)
+        .map_err(|err| Error::Parse(format!("Could not convert to JSON: {}", err)))?;
+    fs::write(&path, json)
+        .map_err(|err| Error::Config(format!("Could not write {}: {}", path, err)))
+}
+
+/// Start tracking time on a task, the task fetched with next when none is given.
+/// A pomodoro waits in the foreground and notifies when it is over.
+pub fn start(
+    config: &Config,
+    target: Option<&str>,
+    pomodoro: Option<u32>,
+) -> Result<String, Error> {
+    let mut log = load(config)?;
+    if let Some(running) = &log.running {
+        return Err(Error::Input(format!(
+            "Already tracking {}, stop it first",
+            running.content
+        )));
+    }
+
+    let id = match target {
+        Some(target) => projects::resolve_ids(config, &[String::from(target)])?.remove(0),
+        None => config.next_id.clone().ok_or_else(|| {
+            Error::Input(String::from("No task to start, get one with next first"))
+        })?,
+    };
+    let item = Cache::load(config)?.find_item(config, &id)?;
+    let minutes = pomodoro.map(|minutes| match minutes {
+        0 => config.pomodoro_minutes.unwrap_or(DEFAULT_POMODORO_MINUTES),
+        minutes => minutes,
+    });
+    let session = Session {
+        item_id: item.id,
+        content: item.content,
+        project_id: item.project_id,
+        started_at: Utc::now(),
+        stopped_at: None,
+        pomodoro_minutes: minutes,
+    };
+    log.running = Some(session.clone());
+    save(config, &log)?;
+
+    let minutes = match minutes {
+        None => return Ok(format!("Started {}", session.content).green().to_string()),
+        Some(minutes) => minutes,
+    };
+    eprintln!(
+        "{}",
+        format!("Pomodoro of {} minutes on {}", minutes, session.content).green()
+    );
+    std::thread::sleep(std::time::Duration::from_secs(minutes as u64 * 60));
+
+    let stopped_at = session.started_at + Duration::minutes(minutes as i64);
+    let report = stop_pomodoro(config, &session, stopped_at)?;
+    notify(&format!("Pomodoro done: {}", session.content));
+    Ok(report)
+}
+
+/// Stop the session of a pomodoro, unless it was stopped from elsewhere in the meantime
+fn stop_pomodoro(
+    config: &Config,
+    session: &Session,
+    stopped_at: DateTime<Utc>,
+) -> Result<String, Error> {
+    let log = load(config)?;
+    let still_running = log.running.as_ref().is_some_and(|running| {
+        running.item_id == session.item_id && running.started_at == session.started_at
+    });
+
+    if still_running {
+        stop_at(config, stopped_at)
+    } else {
+        Ok(format!(
+            "Pomodoro on {} is over, its timer was already stopped",
+            session.content
+        ))
+    }
+}
+
+/// Stop tracking time and record the session
+pub fn stop(config: &Config) -> Result<String, Error> {
+    stop_at(config, Utc::now())
+}
+
+fn stop_at(config: &Config, stopped_at: DateTime<Utc>) -> Result<String, Error> {
+    let mut log = load(config)?;
+    let mut session = log
+        .running
+        .take()
+        .ok_or_else(|| Error::Input(String::from("No timer running")))?;
+
+    session.stopped_at = Some(stopped_at.max(session.started_at));
+    let duration = session.duration(stopped_at);
+    let report = format!(
+        "Stopped {} after {}",
+        session.content,
+        format_duration(duration)
+    );
+    log.sessions.push(session);
+    save(config, &log)?;
+
+    Ok(report.green().to_string())
+}
+
+/// The running timer and time elapsed, with the time left of a pomodoro
+pub fn status(config: &Config) -> Result<String, Error> {
+    let log = load(config)?;
+    let session = match log.running {
+        None => return Ok(String::from("No timer running")),
+        Some(session) => session,
+    };
+
+    let elapsed = session.duration(Utc::now());
+    let mut buffer = format!("{} for {}", session.content, format_duration(elapsed));
+    if let Some(minutes) = session.pomodoro_minutes {
+        let left = Duration::minutes(minutes as i64) - elapsed;
+        if left > Duration::zero() {
+            buffer.push_str(&format!(", {} left of pomodoro", format_duration(left)));
+        } else {
+            buffer.push_str(", pomodoro is over");
+        }
+    }
+    Ok(buffer)
+}
+
+/// Time tracked per project and task, over the last days when given
+pub fn report(config: &Config, days: Option<i64>) -> Result<String, Error> {
+    let log = load(config)?;
+    let now = Utc::now();
+    let since = days.map(|days| now - Duration::days(days));
+    let sessions = log
+        .sessions
+        .iter()
+        .chain(log.running.iter())
+        .filter(|session| since.is_none_or(|since| session.started_at >= since));
+
+    // Project name, then task content, to time worked
+    let mut totals: BTreeMap<String, BTreeMap<String, Duration>> = BTreeMap::new();
+    for session in sessions {
+        let project = config
+            .project_name(&session.project_id)
+            .cloned()
+            .unwrap_or_else(|| session.project_id.clone());
+        *totals
+            .entry(project)
+            .or_default()
+            .entry(session.content.clone())
+            .or_insert_with(Duration::zero) += session.duration(now);
+    }
+
+    if totals.is_empty() {
+        return Ok(String::from("No time tracked"));
+    }
+
+    let mut buffer = String::from("Time tracked").green().to_string();
+    for (project, tasks) in totals {
+        let total = tasks
+            .values()
+            .fold(Duration::zero(), |sum, duration| sum + *duration);
+        buffer.push_str(&format!(
+            "\n\n{} {}",
+            project.bold(),
+            format_duration(total)
+        ));
+        for (content, duration) in tasks {
+            buffer.push_str(&format!(
+                "\n  {:>7}  {}",
+                format_duration(duration),
+                content
+            ));
+        }
+    }
+    Ok(buffer)
+}
+
+/// Ring the terminal bell, and show a desktop notification in terminals that support OSC 9
+fn notify(message: &str) {
+    let mut stdout = std::io::stdout();
+    if stdout.is_terminal() {
+        let _ = write!(stdout, "\x07\x1b]9;{}\x07", message);
+        let _ = stdout.flush();
+    }
+}
+
+/// i.e. 1h 05m, or 12m under an hour
+fn format_duration(duration: Duration) -> String {
+    let minutes = duration.num_minutes().max(0);
+    match minutes / 60 {
+        0 => format!("{}m", minutes),
+        hours => format!("{}h {:02}m", hours, minutes % 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::tests::mock_config;
+    use serde_json::json;
+
+    #[test]
+    fn start_stop_and_report() {
+        let (config, _dir) = mock_config("http://127.0.0.1:9");
+        let config = Config {
+            next_id: Some(String::from("11")),
+            ..config
+        };
+        let item = serde_json::from_value(json!({
+            "id": "11", "project_id": "2", "content": "Write report", "priority": 1,
+            "checked": false, "description": "", "due": null, "is_deleted": false,
+        }))
+        .unwrap();
+        Cache {
+            items: vec![item],
+            ..Cache::default()
+        }
+        .save(&config)
+        .unwrap();
+
+        start(&config, None, None).unwrap();
+        assert_eq!(
+            start(&config, None, None),
+            Err(Error::Input(String::from(
+                "Already tracking Write report, stop it first"
+            )))
+        );
+        assert!(status(&config).unwrap().starts_with("Write report for 0m"));
+        stop(&config).unwrap();
+
+        let mut log = load(&config).unwrap();
+        assert_eq!(log.running, None);
+        log.sessions[0].stopped_at = Some(log.sessions[0].started_at + Duration::minutes(65));
+        save(&config, &log).unwrap();
+
+        assert_eq!(
+            report(&config, Some(7)).unwrap(),
+            "Time tracked\n\nwork 1h 05m\n   1h 05m  Write report"
+        );
+    }
+
+    #[test]
+    fn pomodoro_leaves_other_sessions_running() {
+        let (config, _dir) = mock_config("http://127.0.0.1:9");
+        let session = |item_id: &str, content: &str, started_at: DateTime<Utc>| Session {
+            item_id: String::from(item_id),
+            content: String::from(content),
+            project_id: String::from("2"),
+            started_at,
+            stopped_at: None,
+            pomodoro_minutes: None,
+        };
+        let pomodoro = session("11", "Write report", Utc::now() - Duration::minutes(25));
+        let other = session("12", "Review", Utc::now() - Duration::minutes(5));
+        let log = TimeLog {
+            running: Some(other.clone()),
+            sessions: Vec::new(),
+        };
+        save(&config, &log).unwrap();
+
+        assert_eq!(
+            stop_pomodoro(&config, &pomodoro, Utc::now()).unwrap(),
+            "Pomodoro on Write report is over, its timer was already stopped"
+        );
+        assert_eq!(load(&config).unwrap(), log);
+    }
+}
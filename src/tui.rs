@@ -0,0 +1,411 @@
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::cache::Cache;
+use crate::commands::Changes;
+use crate::config::Config;
+use crate::error::Error;
+use crate::items::Item;
+use crate::{cache, items, projects, request};
+
+const HELP: &str = "←/→ project  ↑/↓ task  c complete  m move  1-4 priority  r reschedule  a add  R refresh  q quit";
+
+/// State of the full screen interface
+struct App {
+    config: Config,
+    cache: Cache,
+    projects: Vec<String>,
+    project_index: usize,
+    items: Vec<Item>,
+    item_state: ListState,
+    prompt: Option<Prompt>,
+    input: String,
+    message: String,
+    /// Set after a mutation, which can print to stderr
+    redraw: bool,
+    quit: bool,
+}
+
+/// Text being entered at the bottom of the screen
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Prompt {
+    Add,
+    Move,
+    Reschedule,
+}
+
+impl Prompt {
+    fn label(&self) -> &str {
+        match self {
+            Prompt::Add => "New task",
+            Prompt::Move => "Move to project",
+            Prompt::Reschedule => "Due",
+        }
+    }
+}
+
+/// Run the full screen interface until the user quits
+pub fn run(config: Config) -> Result<String, Error> {
+    // Item::fmt is reused for the detail pane, which cannot show escape codes
+    colored::control::set_override(false);
+
+    let mut app = App::new(config)?;
+    let mut terminal = ratatui::try_init().map_err(terminal_error)?;
+    let result = app.event_loop(&mut terminal);
+    ratatui::restore();
+
+    result.map(|_| String::new())
+}
+
+impl App {
+    fn new(config: Config) -> Result<App, Error> {
+        let mut projects: Vec<String> = config.projects.keys().cloned().collect();
+        if projects.is_empty() {
+            return Err(Error::Input(String::from(
+                "No projects found, add some with projects sync",
+            )));
+        }
+        projects.sort_by_key(|name| (name != "inbox", name.to_lowercase()));
+
+        let mut app = App {
+            cache: cache::sync(&config)?,
+            config,
+            projects,
+            project_index: 0,
+            items: Vec::new(),
+            item_state: ListState::default(),
+            prompt: None,
+            input: String::new(),
+            message: String::from(HELP),
+            redraw: false,
+            quit: false,
+        };
+        app.load_items();
+        Ok(app)
+    }
+
+    fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> Result<(), Error> {
+        while !self.quit {
+            terminal
+                .draw(|frame| self.draw(frame))
+                .map_err(terminal_error)?;
+
+            if let Event::Key(key) = event::read().map_err(terminal_error)? {
+                if key.kind == KeyEventKind::Press {
+                    self.handle_key(key);
+                }
+            }
+            if self.redraw {
+                self.redraw = false;
+                terminal.clear().map_err(terminal_error)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn project_name(&self) -> &str {
+        &self.projects[self.project_index]
+    }
+
+    fn selected_item(&self) -> Option<&Item> {
+        self.item_state.selected().and_then(|i| self.items.get(i))
+    }
+
+    /// Show the cached items of the selected project, sorted by value
+    fn load_items(&mut self) {
+        let items = match projects::project_id(&self.config, self.project_name()) {
+            Ok(project_id) => self.cache.items_for_project(&project_id),
+            Err(err) => {
+                self.message = err.to_string();
+                Vec::new()
+            }
+        };
+        self.items = items::sort_by_value(items, &self.config);
+
+        let selected = match self.item_state.selected() {
+            _ if self.items.is_empty() => None,
+            Some(index) => Some(index.min(self.items.len() - 1)),
+            None => Some(0),
+        };
+        self.item_state.select(selected);
+    }
+
+    fn refresh(&mut self) {
+        match cache::sync(&self.config) {
+            Ok(cache) => self.cache = cache,
+            Err(err) => self.message = err.to_string(),
+        }
+        self.load_items();
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        match self.prompt {
+            Some(prompt) => self.handle_prompt_key(prompt, key),
+            None => self.handle_normal_key(key),
+        }
+    }
+
+    fn handle_normal_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Left | KeyCode::Char('h') => self.select_project(self.projects.len() - 1),
+            KeyCode::Right | KeyCode::Char('l') => self.select_project(1),
+            KeyCode::Up | KeyCode::Char('k') => self.select_item(self.items.len().max(1) - 1),
+            KeyCode::Down | KeyCode::Char('j') => self.select_item(1),
+            KeyCode::Char('R') => self.refresh(),
+            KeyCode::Char('a') => self.prompt = Some(Prompt::Add),
+            KeyCode::Char('m') if self.selected_item().is_some() => {
+                self.prompt = Some(Prompt::Move)
+            }
+            KeyCode::Char('r') if self.selected_item().is_some() => {
+                self.prompt = Some(Prompt::Reschedule)
+            }
+            KeyCode::Char('c') => self.mutate(request::close_item),
+            // Keys match the p1 to p4 labels in Todoist, where p1 is priority 4 in the API
+            KeyCode::Char(key @ '1'..='4') => {
+                let priority = 5 - key.to_digit(10).unwrap_or(4) as u8;
+                self.mutate(|config, item| request::update_item_priority(config, item, priority))
+            }
+            _ => (),
+        }
+    }
+
+    fn handle_prompt_key(&mut self, prompt: Prompt, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => {
+                self.prompt = None;
+                self.input.clear();
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Char(character) => self.input.push(character),
+            KeyCode::Enter => {
+                let input = std::mem::take(&mut self.input);
+                self.prompt = None;
+                if input.trim().is_empty() {
+                    return;
+                }
+                match prompt {
+                    Prompt::Add => {
+                        let project = self.project_name().to_owned();
+                        let result = projects::add_item_to_project(
+                            self.config.clone(),
+                            &input,
+                            &project,
+                            &Changes::default(),
+                        );
+                        self.finish(result);
+                    }
+                    Prompt::Move => {
+                        self.mutate(|config, item| request::move_item(config, item, &input))
+                    }
+                    Prompt::Reschedule => {
+                        self.mutate(|config, item| request::update_item_due(config, item, &input))
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Step through projects, wrapping around
+    fn select_project(&mut self, step: usize) {
+        self.project_index = (self.project_index + step) % self.projects.len();
+        self.item_state.select(None);
+        self.load_items();
+    }
+
+    /// Step through items, wrapping around
+    fn select_item(&mut self, step: usize) {
+        if let Some(index) = self.item_state.selected() {
+            self.item_state
+                .select(Some((index + step) % self.items.len()));
+        }
+    }
+
+    /// Apply a request function to the selected item
+    fn mutate<F>(&mut self, request: F)
+    where
+        F: FnOnce(Config, Item) -> Result<String, Error>,
+    {
+        if let Some(item) = self.selected_item().cloned() {
+            let result = request(self.config.clone(), item);
+            self.finish(result);
+        }
+    }
+
+    fn finish(&mut self, result: Result<String, Error>) {
+        self.redraw = true;
+        match result {
+            Ok(_) => {
+                self.message = String::from("✓");
+                self.refresh();
+            }
+            Err(err) => self.message = err.to_string(),
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(3), Constraint::Length(1)]).areas(frame.area());
+        let [sidebar, tasks] =
+            Layout::horizontal([Constraint::Percentage(20), Constraint::Percentage(80)])
+                .areas(main);
+        let [list, detail] =
+            Layout::vertical([Constraint::Percentage(65), Constraint::Percentage(35)]).areas(tasks);
+
+        let project_items: Vec<ListItem> = self
+            .projects
+            .iter()
+            .map(|name| ListItem::new(name.as_str()))
+            .collect();
+        let mut project_state = ListState::default().with_selected(Some(self.project_index));
+        frame.render_stateful_widget(
+            List::new(project_items)
+                .block(Block::default().borders(Borders::ALL).title("Projects"))
+                .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+                .highlight_symbol("> "),
+            sidebar,
+            &mut project_state,
+        );
+
+        let task_items: Vec<ListItem> = self
+            .items
+            .iter()
+            .map(|item| ListItem::new(item.content.as_str()).style(priority_style(item)))
+            .collect();
+        frame.render_stateful_widget(
+            List::new(task_items)
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(format!("Tasks for {}", self.project_name())),
+                )
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+                .highlight_symbol("> "),
+            list,
+            &mut self.item_state,
+        );
+
+        let details = self
+            .selected_item()
+            .map(|item| item.fmt(&self.config).trim().to_owned())
+            .unwrap_or_default();
+        frame.render_widget(
+            Paragraph::new(details)
+                .wrap(Wrap { trim: false })
+                .block(Block::default().borders(Borders::ALL).title("Details")),
+            detail,
+        );
+
+        let status_line = match self.prompt {
+            Some(prompt) => Line::from(format!("{}: {}_", prompt.label(), self.input)),
+            None => Line::from(self.message.as_str()),
+        };
+        frame.render_widget(Paragraph::new(status_line), status);
+    }
+}
+
+fn priority_style(item: &Item) -> Style {
+    match item.priority {
+        2 => Style::default().fg(Color::Blue),
+        3 => Style::default().fg(Color::Yellow),
+        4 => Style::default().fg(Color::Red),
+        _ => Style::default(),
+    }
+}
+
+fn terminal_error(err: std::io::Error) -> Error {
+    Error::Terminal(format!("Could not use terminal: {}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::tests::mock_config;
+    use crate::queue;
+    use ratatui::crossterm::event::KeyModifiers;
+    use serde_json::json;
+
+    fn press(app: &mut App, code: KeyCode) {
+        app.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+    }
+
+    #[test]
+    fn navigation_wraps_and_prompts_collect_input() {
+        let (config, _dir) = mock_config("http://127.0.0.1:9");
+        let item = |id: &str, priority: u8| {
+            serde_json::from_value(json!({
+                "id": id, "project_id": "1", "content": id, "priority": priority,
+                "checked": false, "description": "", "due": null, "is_deleted": false,
+            }))
+            .unwrap()
+        };
+        Cache {
+            sync_token: Some(String::from("abc")),
+            items: vec![item("low", 1), item("high", 4)],
+            ..Cache::default()
+        }
+        .save(&config)
+        .unwrap();
+        let mut app = App::new(config).unwrap();
+
+        assert_eq!(app.project_name(), "inbox");
+        assert_eq!(app.selected_item().unwrap().id, "high");
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Down);
+        assert_eq!(app.selected_item().unwrap().id, "high");
+
+        press(&mut app, KeyCode::Right);
+        assert_eq!(app.project_name(), "work");
+        assert!(app.selected_item().is_none());
+
+        press(&mut app, KeyCode::Char('a'));
+        press(&mut app, KeyCode::Char('x'));
+        assert_eq!((app.prompt, app.input.as_str()), (Some(Prompt::Add), "x"));
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.prompt, None);
+
+        press(&mut app, KeyCode::Char('q'));
+        assert!(app.quit);
+    }
+
+    #[test]
+    fn complete_leaves_focus_on_other_tasks() {
+        let (config, _dir) = mock_config("http://127.0.0.1:9");
+        let config = Config {
+            next_id: Some(String::from("focused")),
+            focus: vec![String::from("focused"), String::from("other")],
+            ..config
+        };
+        config.clone().save().unwrap();
+        let item = |id: &str, priority: u8| {
+            serde_json::from_value(json!({
+                "id": id, "project_id": "1", "content": id, "priority": priority,
+                "checked": false, "description": "", "due": null, "is_deleted": false,
+            }))
+            .unwrap()
+        };
+        Cache {
+            sync_token: Some(String::from("abc")),
+            items: vec![item("focused", 1), item("other", 4)],
+            ..Cache::default()
+        }
+        .save(&config)
+        .unwrap();
+        let mut app = App::new(config.clone()).unwrap();
+
+        assert_eq!(app.selected_item().unwrap().id, "other");
+        press(&mut app, KeyCode::Char('c'));
+
+        let saved = Config::load(&config.path).unwrap();
+        assert_eq!(saved.next_id, Some(String::from("focused")));
+        assert_eq!(saved.focus, vec![String::from("focused")]);
+        assert_eq!(queue::load(&config).unwrap()[0].kind, "item_close");
+    }
+}
@@ -0,0 +1,399 @@
+use chrono::{DateTime, Duration, Utc};
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use crate::cache::Cache;
+use crate::commands::{Command, Status};
+use crate::config::Config;
+use crate::error::Error;
+use crate::items::Item;
+use crate::{config, queue};
+
+/// Minutes a deletion can be undone when undo_minutes is not set in config
+const DEFAULT_UNDO_MINUTES: u32 = 30;
+
+/// Items removed by the last delete, kept so that they can be recreated.
+/// Subtasks deleted along with their parent are included, parents first.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+struct Deleted {
+    deleted_at: DateTime<Utc>,
+    items: Vec<Item>,
+}
+
+/// Path of the snapshot file, tod.deleted in the same directory as tod.cfg
+fn path(config: &Config) -> String {
+    Path::new(&config.path)
+        .with_extension("deleted")
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn load(config: &Config) -> Result<Option<Deleted>, Error> {
+    let path = path(config);
+    if !Path::new(&path).exists() {
+        return Ok(None);
+    }
+
+    let json = fs::read_to_string(&path)
+        .map_err(|err| Error::Config(format!("Could not read {}: {}", path, err)))?;
+    serde_json::from_str(&json)
+        .map(Some)
+        .map_err(|err| Error::Parse(format!("Could not parse {}: {}", path, err)))
+}
+
+fn save(config: &Config, deleted: Option<&Deleted>) -> Result<(), Error> {
+    let path = path(config);
+    match deleted {
+        None => match fs::remove_file(&path) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                Err(Error::Config(format!("Could not remove {}: {}", path, err)))
+            }
+            _ => Ok(()),
+        },
+        Some(deleted) => {
+            let json = serde_json::to_string_pretty(deleted)
+                .map_err(|err| Error::Parse(format!("Could not convert to JSON: {}", err)))?;
+            fs::write(&path, json)
+                .map_err(|err| Error::Config(format!("Could not write {}: {}", path, err)))
+        }
+    }
+}
+
+/// Delete tasks by id after confirming, keeping a snapshot for undo
+pub fn delete_items(config: &Config, ids: &[String], confirmed: bool) -> Result<String, Error> {
+    let cache = Cache::load(config)?;
+    let mut items: Vec<Item> = Vec::new();
+    for id in ids {
+        items.push(cache.find_item(config, id)?);
+    }
+
+    // Subtasks are deleted with their parent, so only the topmost items are sent
+    let ids: HashSet<&String> = items.iter().map(|item| &item.id).collect();
+    let subtasks = subtasks_of(&cache, &ids);
+    let subtask_ids: HashSet<&String> = subtasks.iter().map(|item| &item.id).collect();
+    let items: Vec<Item> = items
+        .into_iter()
+        .filter(|item| !subtask_ids.contains(&item.id))
+        .collect();
+
+    if !confirmed {
+        for item in items.iter() {
+            println!("{}", item.fmt(config));
+        }
+        let question = match subtasks.len() {
+            0 => format!("\nDelete {} task(s)? (y/n)", items.len()),
+            count => format!(
+                "\nDelete {} task(s) and {} subtask(s)? (y/n)",
+                items.len(),
+                count
+            ),
+        };
+        let answer = config::get_input(&question)?;
+        if !matches!(answer.to_lowercase().as_str(), "y" | "yes") {
+            return Ok(String::from("Nothing deleted"));
+        }
+    }
+
+    let commands: Vec<Command> = items
+        .iter()
+        .map(|item| Command::item_delete(&item.id))
+        .collect();
+    let response = queue::submit_all(config, commands.clone())?;
+
+    let mut buffer = String::new();
+    let mut deleted: Vec<Item> = Vec::new();
+    for (item, command) in items.into_iter().zip(commands) {
+        let status = response.status(&command.uuid);
+        let line = match &status {
+            Status::Ok => format!("✓ {}", item.content).green(),
+            Status::Queued => format!("… {} (queued)", item.content).yellow(),
+            Status::Failed(message) => format!("✗ {}: {}", item.content, message).red(),
+        };
+        buffer.push_str(&format!("\n{}", line));
+        if !matches!(status, Status::Failed(_)) {
+            deleted.push(item);
+        }
+    }
+
+    if !deleted.is_empty() {
+        let deleted_ids: HashSet<&String> = deleted.iter().map(|item| &item.id).collect();
+        let deleted_subtasks = subtasks_of(&cache, &deleted_ids);
+        let snapshot = Deleted {
+            deleted_at: Utc::now(),
+            items: [deleted, deleted_subtasks].concat(),
+        };
+        save(config, Some(&snapshot))?;
+        buffer.push_str(&format!(
+            "\nRun undo within {} minutes to restore",
+            undo_minutes(config)
+        ));
+    }
+
+    Ok(buffer.trim_start().to_owned())
+}
+
+/// Recreate the tasks removed by the last delete, if it was within the undo window
+pub fn undo(config: &Config) -> Result<String, Error> {
+    let deleted = match load(config)? {
+        Some(deleted) if !is_expired(config, &deleted, Utc::now()) => deleted,
+        _ => return Err(Error::Input(String::from("Nothing to undo"))),
+    };
+
+    // Children refer to the temp id of their restored parent
+    let mut temp_ids: HashMap<&String, String> = HashMap::new();
+    let mut commands: Vec<Command> = Vec::new();
+    for item in deleted.items.iter() {
+        let parent_id = item
+            .parent_id
+            .as_ref()
+            .map(|id| temp_ids.get(id).unwrap_or(id).as_str());
+        let command = Command::item_restore(item, parent_id);
+        temp_ids.insert(&item.id, command.temp_id.clone().unwrap_or_default());
+        commands.push(command);
+    }
+    let response = queue::submit_all(config, commands.clone())?;
+
+    // Items that failed, and subtasks of items that failed, stay in the snapshot to retry
+    let mut failed: HashSet<&String> = HashSet::new();
+    let mut buffer = String::new();
+    for (item, command) in deleted.items.iter().zip(commands) {
+        let status = response.status(&command.uuid);
+        let line = match &status {
+            Status::Ok => format!("✓ Restored {}", item.content).green(),
+            Status::Queued => format!("… {} (queued)", item.content).yellow(),
+            Status::Failed(message) => format!("✗ {}: {}", item.content, message).red(),
+        };
+        buffer.push_str(&format!("\n{}", line));
+        let parent_failed = item
+            .parent_id
+            .as_ref()
+            .is_some_and(|id| failed.contains(id));
+        if matches!(status, Status::Failed(_)) || parent_failed {
+            failed.insert(&item.id);
+        }
+    }
+
+    let remaining: Vec<Item> = deleted
+        .items
+        .iter()
+        .filter(|item| failed.contains(&item.id))
+        .map(|item| {
+            // A restored parent has a new id
+            let parent_id = item.parent_id.as_ref().map(|id| {
+                temp_ids
+                    .get(id)
+                    .and_then(|temp_id| response.temp_id_mapping.get(temp_id))
+                    .unwrap_or(id)
+                    .to_owned()
+            });
+            Item {
+                parent_id,
+                ..item.clone()
+            }
+        })
+        .collect();
+    if remaining.is_empty() {
+        save(config, None)?;
+    } else {
+        let snapshot = Deleted {
+            deleted_at: deleted.deleted_at,
+            items: remaining,
+        };
+        save(config, Some(&snapshot))?;
+        buffer.push_str("\nRun undo again to retry");
+    }
+    Ok(buffer.trim_start().to_owned())
+}
+
+/// Cached items below the given items, each after its parent
+fn subtasks_of(cache: &Cache, ids: &HashSet<&String>) -> Vec<Item> {
+    let mut parents: HashSet<&String> = ids.clone();
+    let mut subtasks: Vec<&Item> = Vec::new();
+    loop {
+        let children: Vec<&Item> = cache
+            .items
+            .iter()
+            .filter(|item| !subtasks.iter().any(|subtask| subtask.id == item.id))
+            .filter(|item| {
+                item.parent_id
+                    .as_ref()
+                    .is_some_and(|parent_id| parents.contains(parent_id))
+            })
+            .collect();
+        if children.is_empty() {
+            return subtasks.into_iter().cloned().collect();
+        }
+        parents.extend(children.iter().map(|item| &item.id));
+        subtasks.extend(children);
+    }
+}
+
+fn undo_minutes(config: &Config) -> u32 {
+    config.undo_minutes.unwrap_or(DEFAULT_UNDO_MINUTES)
+}
+
+fn is_expired(config: &Config, deleted: &Deleted, now: DateTime<Utc>) -> bool {
+    now - deleted.deleted_at > Duration::minutes(undo_minutes(config) as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::tests::all_ok;
+    use crate::config::tests::mock_config;
+    use mockito::Matcher;
+    use serde_json::json;
+
+    #[test]
+    fn undo_window_comes_from_config() {
+        let (config, _dir) = mock_config("http://localhost");
+        let config = Config {
+            undo_minutes: Some(5),
+            ..config
+        };
+        let deleted = Deleted {
+            deleted_at: Utc::now(),
+            items: Vec::new(),
+        };
+
+        assert!(!is_expired(
+            &config,
+            &deleted,
+            Utc::now() + Duration::minutes(4)
+        ));
+        assert!(is_expired(
+            &config,
+            &deleted,
+            Utc::now() + Duration::minutes(6)
+        ));
+    }
+
+    #[test]
+    fn undo_restores_subtasks_under_the_restored_parent() {
+        let mut server = mockito::Server::new();
+        let (config, _dir) = mock_config(&server.url());
+        let item = |id: &str, parent_id: Option<&str>| -> Item {
+            serde_json::from_value(json!({
+                "id": id, "project_id": "2", "section_id": "7", "parent_id": parent_id,
+                "content": format!("Task {}", id), "priority": 1, "checked": false,
+                "description": "", "due": null, "is_deleted": false,
+            }))
+            .unwrap()
+        };
+        Cache {
+            items: vec![
+                item("10", None),
+                item("11", Some("10")),
+                item("12", Some("11")),
+            ],
+            ..Cache::default()
+        }
+        .save(&config)
+        .unwrap();
+        let delete = server
+            .mock("POST", "/sync/v9/sync")
+            .match_body(Matcher::PartialJson(json!({
+                "commands": [{"type": "item_delete", "args": {"id": "10"}}]
+            })))
+            .with_body_from_request(all_ok)
+            .create();
+
+        let targets = [String::from("11"), String::from("10")];
+        delete_items(&config, &targets, true).unwrap();
+        delete.assert();
+
+        let restore = server
+            .mock("POST", "/sync/v9/sync")
+            .match_body(Matcher::PartialJson(json!({
+                "commands": [
+                    {"type": "item_add", "args": {"content": "Task 10", "section_id": "7"}},
+                    {"type": "item_add", "args": {"content": "Task 11", "section_id": "7"}},
+                    {"type": "item_add", "args": {"content": "Task 12", "section_id": "7"}},
+                ]
+            })))
+            .with_body_from_request(|request| {
+                let body: serde_json::Value =
+                    serde_json::from_slice(request.body().unwrap()).unwrap();
+                let commands = body["commands"].as_array().unwrap();
+                assert_eq!(commands[0]["args"].get("parent_id"), None);
+                assert_eq!(commands[1]["args"]["parent_id"], commands[0]["temp_id"]);
+                assert_eq!(commands[2]["args"]["parent_id"], commands[1]["temp_id"]);
+                all_ok(request)
+            })
+            .create();
+
+        let output = undo(&config).unwrap();
+        restore.assert();
+        assert_eq!(
+            output,
+            "✓ Restored Task 10\n✓ Restored Task 11\n✓ Restored Task 12"
+        );
+    }
+
+    #[test]
+    fn failed_restores_are_kept_for_another_undo() {
+        let mut server = mockito::Server::new();
+        let (config, _dir) = mock_config(&server.url());
+        let item = |id: &str, parent_id: Option<&str>| -> Item {
+            serde_json::from_value(json!({
+                "id": id, "project_id": "2", "parent_id": parent_id,
+                "content": format!("Task {}", id), "priority": 1, "checked": false,
+                "description": "", "due": null, "is_deleted": false,
+            }))
+            .unwrap()
+        };
+        let snapshot = Deleted {
+            deleted_at: Utc::now(),
+            items: vec![item("10", None), item("11", Some("10")), item("12", None)],
+        };
+        save(&config, Some(&snapshot)).unwrap();
+        server
+            .mock("POST", "/sync/v9/sync")
+            .with_body_from_request(|request| {
+                let body: serde_json::Value =
+                    serde_json::from_slice(request.body().unwrap()).unwrap();
+                let commands = body["commands"].as_array().unwrap();
+                let uuid = |index: usize| commands[index]["uuid"].as_str().unwrap();
+                json!({
+                    "sync_status": {
+                        uuid(0): "ok",
+                        uuid(1): {"error_code": 20, "error": "Too many items"},
+                        uuid(2): "ok",
+                    },
+                    "temp_id_mapping": {commands[0]["temp_id"].as_str().unwrap(): "50"},
+                })
+                .to_string()
+                .into()
+            })
+            .create();
+
+        let output = undo(&config).unwrap();
+
+        assert_eq!(
+            output,
+            format!(
+                "{}\n{}\n{}\nRun undo again to retry",
+                "✓ Restored Task 10".green(),
+                "✗ Task 11: Too many items".red(),
+                "✓ Restored Task 12".green()
+            )
+        );
+        let remaining = load(&config).unwrap().unwrap();
+        assert_eq!(remaining.items.len(), 1);
+        assert_eq!(remaining.items[0].id, "11");
+        assert_eq!(remaining.items[0].parent_id, Some(String::from("50")));
+    }
+
+    #[test]
+    fn undo_without_deletion() {
+        let (config, _dir) = mock_config("http://localhost");
+
+        assert_eq!(
+            undo(&config),
+            Err(Error::Input(String::from("Nothing to undo")))
+        );
+    }
+}